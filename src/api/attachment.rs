@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A file uploaded through `/api/v1/attachments`, referenced by send requests
+/// with its id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    /// Hex SHA-256 of the content, which names the stored file
+    pub sha256: String,
+    /// User of the token it was uploaded with, none for anonymous uploads
+    pub user: Option<String>,
+    pub filename: Option<String>,
+    pub media_type: String,
+    pub size: u64,
+    pub created_at: DateTime<Utc>,
+    /// Last time a send request used the attachment
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[macro_export]
+macro_rules! attachments {
+    ($config:expr, $attachments:expr) => {
+        self::routes::attachment_route::upload($config.clone())
+            .and(with_attachments($attachments.clone()))
+            .and(with_config($config.clone()))
+            .and_then(self::handlers::attachment_handler::upload)
+            .or(self::routes::attachment_route::list($config.clone())
+                .and(with_attachments($attachments.clone()))
+                .and_then(self::handlers::attachment_handler::list))
+            .or(self::routes::attachment_route::get($config.clone())
+                .and(with_attachments($attachments.clone()))
+                .and_then(self::handlers::attachment_handler::get))
+            .or(self::routes::attachment_route::content($config.clone())
+                .and(with_attachments($attachments.clone()))
+                .and_then(self::handlers::attachment_handler::content))
+            .or(self::routes::attachment_route::delete($config)
+                .and(with_attachments($attachments))
+                .and_then(self::handlers::attachment_handler::delete))
+    };
+}
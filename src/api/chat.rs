@@ -0,0 +1,56 @@
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::config::Persona;
+
+/// Body of a send request, the same for every vendor
+#[derive(Debug, Deserialize)]
+pub struct ChatRequestIntermediate {
+    pub uuid: String,
+    pub message: String,
+    pub image: Option<String>,
+    /// Files sent along, uploaded attachment ids or http(s) or `data:` URLs.
+    /// Older clients send a single one as `attachment`.
+    #[serde(default, alias = "attachment", deserialize_with = "super::one_or_many")]
+    pub attachments: Option<Vec<String>>,
+    /// Must name the user of the bearer token when given
+    pub user: Option<String>,
+    pub plugins: Option<Vec<String>>,
+    /// Persona answering this and later messages of the conversation, the
+    /// default one when empty
+    pub persona: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ChatRequest {
+    pub uuid: Arc<String>,
+    pub message: Arc<String>,
+    pub image: Option<Arc<String>>,
+    pub attachments: Option<Vec<String>>,
+    /// User of the bearer token, or the owner of a schedule or watchlist
+    pub user: Option<Arc<String>>,
+    pub plugins: Option<Vec<String>>,
+    /// Model replacing the configured one, for requests made by the server
+    pub model: Option<Arc<String>>,
+    /// Persona of the conversation, looked up when the request is dequeued
+    pub persona: Option<Arc<Persona>>,
+    /// Answered with no client listening, e.g. a schedule without a
+    /// conversation, so nothing is published over SSE
+    pub background: bool,
+}
+
+impl From<ChatRequestIntermediate> for ChatRequest {
+    fn from(intermediate: ChatRequestIntermediate) -> Self {
+        ChatRequest {
+            uuid: Arc::new(intermediate.uuid),
+            message: Arc::new(intermediate.message),
+            image: intermediate.image.map(Arc::new),
+            attachments: intermediate.attachments,
+            user: intermediate.user.map(Arc::new),
+            plugins: intermediate.plugins,
+            model: None,
+            persona: None,
+            background: false,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+#[macro_export]
+macro_rules! metrics {
+    ($sse:expr, $mem:expr) => {
+        self::routes::metrics_route::metrics()
+            .and(with_sse($sse))
+            .and(with_memory($mem))
+            .and_then(self::handlers::metrics_handler::scrape)
+    };
+}
@@ -1,3 +1,55 @@
+pub mod attachment;
+pub mod chat;
 pub mod claude;
+pub mod metrics;
 pub mod openai;
+pub mod persona;
+pub mod schedule;
 pub mod sse;
+pub mod watchlist;
+pub mod webhook;
+
+use serde::{Deserialize, Deserializer};
+
+/// A list of strings, or a single one as older clients send it
+fn one_or_many<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(
+        Option::<OneOrMany>::deserialize(deserializer)?.map(|value| match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chat::ChatRequestIntermediate;
+    use serde_json::json;
+
+    fn attachments(body: serde_json::Value) -> Option<Vec<String>> {
+        serde_json::from_value::<ChatRequestIntermediate>(body)
+            .unwrap()
+            .attachments
+    }
+
+    #[test]
+    fn attachment_takes_one_or_many() {
+        let body = json!({ "uuid": "c", "message": "m" });
+        assert_eq!(attachments(body), None);
+        let body = json!({ "uuid": "c", "message": "m", "attachment": "a" });
+        assert_eq!(attachments(body), Some(vec![String::from("a")]));
+        let body = json!({ "uuid": "c", "message": "m", "attachments": ["a", "b"] });
+        assert_eq!(attachments(body).map(|ids| ids.len()), Some(2));
+        let body = json!({ "uuid": "c", "message": "m", "attachments": "a", "attachment": "b" });
+        assert!(serde_json::from_value::<ChatRequestIntermediate>(body).is_err());
+    }
+}
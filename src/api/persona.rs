@@ -0,0 +1,8 @@
+#[macro_export]
+macro_rules! personas {
+    ($config:expr) => {
+        self::routes::persona_route::list()
+            .and(with_config($config))
+            .and_then(self::handlers::persona_handler::list)
+    };
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleRequest {
+    pub name: Option<String>,
+    /// Five field cron expression read in `schedules.timezone`, e.g. `0 9 * * 1-5`
+    pub cron: String,
+    /// `openai` or `claude`
+    pub vendor: String,
+    /// Model replacing the vendor's configured one
+    pub model: Option<String>,
+    pub prompt: String,
+    /// Conversation receiving the answers, a conversation of the schedule's own
+    /// when missing
+    pub conversation: Option<String>,
+    /// Plugins offered to the model, narrowing the user's list like a send request
+    pub plugins: Option<Vec<String>>,
+}
+
+/// A prompt sent to a vendor on every occurrence of its cron expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    pub name: String,
+    pub cron: String,
+    pub vendor: String,
+    pub model: Option<String>,
+    pub prompt: String,
+    pub conversation: Option<String>,
+    /// Owner, the user of the token that created it, whose plugins it may use
+    pub user: Option<String>,
+    pub plugins: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub next_run: Option<DateTime<Utc>>,
+    /// Latest runs, oldest first
+    #[serde(default)]
+    pub runs: Vec<Run>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: RunStatus,
+    /// The answer, or the reason the run failed
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Ok,
+    Failed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleQuery {
+    pub conversation: Option<String>,
+}
+
+#[macro_export]
+macro_rules! schedules {
+    ($config:expr, $schedules:expr, $pipeline:expr) => {
+        self::routes::schedule_route::create($config.clone())
+            .and(with_schedules($schedules.clone()))
+            .and(with_config($config.clone()))
+            .and_then(self::handlers::schedule_handler::create)
+            .or(self::routes::schedule_route::list($config.clone())
+                .and(with_schedules($schedules.clone()))
+                .and_then(self::handlers::schedule_handler::list))
+            .or(self::routes::schedule_route::get($config.clone())
+                .and(with_schedules($schedules.clone()))
+                .and_then(self::handlers::schedule_handler::get))
+            .or(self::routes::schedule_route::run($config.clone())
+                .and(with_schedules($schedules.clone()))
+                .and(with_pipeline($pipeline))
+                .and_then(self::handlers::schedule_handler::run))
+            .or(self::routes::schedule_route::delete($config)
+                .and(with_schedules($schedules))
+                .and_then(self::handlers::schedule_handler::delete))
+    };
+}
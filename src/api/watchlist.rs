@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchlistRequest {
+    /// Conversation receiving the daily report over SSE and in its history
+    pub conversation: Option<String>,
+    pub name: Option<String>,
+    /// Stock codes on TWSE or TPEx, e.g. 2330
+    pub stocks: Vec<String>,
+}
+
+/// A saved watchlist, reported on every trading day after the market closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub id: String,
+    pub name: String,
+    pub conversation: Option<String>,
+    /// Owner, the user of the token that created it. Its plugin configuration
+    /// gathers the report's data.
+    pub user: Option<String>,
+    pub stocks: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatchlistQuery {
+    pub conversation: Option<String>,
+}
+
+#[macro_export]
+macro_rules! watchlists {
+    ($config:expr, $watchlists:expr) => {
+        self::routes::watchlist_route::create($config.clone())
+            .and(with_watchlists($watchlists.clone()))
+            .and(with_config($config.clone()))
+            .and_then(self::handlers::watchlist_handler::create)
+            .or(self::routes::watchlist_route::list($config.clone())
+                .and(with_watchlists($watchlists.clone()))
+                .and_then(self::handlers::watchlist_handler::list))
+            .or(self::routes::watchlist_route::delete($config)
+                .and(with_watchlists($watchlists))
+                .and_then(self::handlers::watchlist_handler::delete))
+    };
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One event sent to one webhook endpoint, after its last attempt
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    /// Also sent in `X-Artificial-Delivery`, the same for every attempt
+    pub id: String,
+    pub event: String,
+    pub url: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// HTTP status of the last answer, missing when the endpoint was unreachable
+    pub response_status: Option<u16>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub event: Option<String>,
+}
+
+#[macro_export]
+macro_rules! webhooks {
+    () => {
+        self::routes::webhook_route::deliveries()
+            .and_then(self::handlers::webhook_handler::deliveries)
+    };
+}
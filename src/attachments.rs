@@ -0,0 +1,247 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::api::attachment::Attachment;
+use crate::config::AttachmentsConfig;
+use crate::store::JsonStore;
+use crate::vendor;
+
+pub type Attachments = Arc<AttachmentStore>;
+
+/// Uploaded files. Contents are stored once in `blobs/`, named by their
+/// SHA-256, and every upload gets a metadata record of its own.
+pub struct AttachmentStore {
+    blobs: PathBuf,
+    records: JsonStore<Attachment>,
+    /// Held while files are written or swept so a sweep never deletes the
+    /// content of an upload in progress
+    files: tokio::sync::Mutex<()>,
+}
+
+impl AttachmentStore {
+    pub fn open(config: &AttachmentsConfig) -> Result<Self> {
+        let blobs = config.path.join("blobs");
+        std::fs::create_dir_all(&blobs)
+            .with_context(|| format!("unable to create {}", blobs.display()))?;
+        Ok(AttachmentStore {
+            blobs,
+            records: JsonStore::open(&config.path.join("attachments.json"))?,
+            files: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Attachment> {
+        self.records.list()
+    }
+
+    /// Bytes of the attachments `user` keeps, anonymous ones share a quota
+    fn usage(&self, user: Option<&str>) -> u64 {
+        self.records
+            .list()
+            .iter()
+            .filter(|attachment| attachment.user.as_deref() == user)
+            .map(|attachment| attachment.size)
+            .sum()
+    }
+
+    /// Store an upload of `user`, failing with `QuotaExceeded` when it would
+    /// take the user's attachments past `quota` bytes. The quota is checked
+    /// under the same lock as the write, so concurrent uploads cannot both
+    /// slip under it.
+    pub async fn save(
+        &self,
+        user: Option<String>,
+        filename: Option<String>,
+        media_type: String,
+        data: Vec<u8>,
+        quota: u64,
+    ) -> Result<Attachment> {
+        let sha256 = hex::encode(Sha256::digest(&data));
+        let path = self.blobs.join(&sha256);
+        let _files = self.files.lock().await;
+        let used = self.usage(user.as_deref());
+        if used + data.len() as u64 > quota {
+            bail!(QuotaExceeded { quota, used });
+        }
+        if !tokio::fs::try_exists(&path).await? {
+            let temporary = self.blobs.join(format!("{}.tmp", sha256));
+            // Write to a temporary file first so a crash never leaves half a file
+            let written = match tokio::fs::write(&temporary, &data).await {
+                Ok(()) => tokio::fs::rename(&temporary, &path).await,
+                Err(err) => Err(err),
+            };
+            written.with_context(|| format!("unable to save {}", path.display()))?;
+        }
+        let attachment = Attachment {
+            id: uuid::Uuid::new_v4().to_string(),
+            sha256,
+            user,
+            filename,
+            media_type,
+            size: data.len() as u64,
+            created_at: Utc::now(),
+            used_at: None,
+        };
+        self.records
+            .insert(attachment.id.clone(), attachment.clone())
+            .await?;
+        Ok(attachment)
+    }
+
+    pub async fn read(&self, attachment: &Attachment) -> Result<Vec<u8>> {
+        let path = self.blobs.join(&attachment.sha256);
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("unable to read {}", path.display()))
+    }
+
+    /// The attachment `id` if `user` may use it: anonymous uploads are
+    /// available to anyone holding their id, those of a user only to that user
+    pub fn get_for(&self, id: &str, user: Option<&str>) -> Option<Attachment> {
+        self.records
+            .get(id)
+            .filter(|attachment| attachment.user.is_none() || attachment.user.as_deref() == user)
+    }
+
+    /// Content of the attachment `id` for a message of `user`, marking it used
+    pub async fn use_for(&self, id: &str, user: Option<&str>) -> Result<(Attachment, Vec<u8>)> {
+        let attachment = self
+            .get_for(id, user)
+            .ok_or_else(|| anyhow!("attachment {} not found", id))?;
+        self.records
+            .update(id, |attachment| attachment.used_at = Some(Utc::now()))
+            .await?;
+        let data = self.read(&attachment).await?;
+        Ok((attachment, data))
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<Option<Attachment>> {
+        let _files = self.files.lock().await;
+        let removed = self.records.remove(id).await?;
+        if let Some(attachment) = &removed {
+            if !self.referenced().contains(&attachment.sha256) {
+                tokio::fs::remove_file(self.blobs.join(&attachment.sha256)).await?;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Delete the attachments neither uploaded nor used within `retention`,
+    /// then every file no attachment refers to. Returns the attachments deleted.
+    pub async fn collect(&self, retention: chrono::Duration) -> Result<usize> {
+        let _files = self.files.lock().await;
+        let cutoff = Utc::now() - retention;
+        let removed = self
+            .records
+            .remove_where(|_, attachment| {
+                attachment.used_at.unwrap_or(attachment.created_at) < cutoff
+            })
+            .await?
+            .len();
+
+        let referenced = self.referenced();
+        let mut entries = tokio::fs::read_dir(&self.blobs).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if !referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+                tokio::fs::remove_file(entry.path()).await?;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn referenced(&self) -> HashSet<String> {
+        self.records
+            .list()
+            .into_iter()
+            .map(|attachment| attachment.sha256)
+            .collect()
+    }
+}
+
+/// An upload past the user's attachment quota
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    pub quota: u64,
+    pub used: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "attachment quota of {} bytes exceeded, {} bytes in use",
+            self.quota, self.used
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Media type of a file from its contents: one of the image types the vendors
+/// take, PDF or UTF-8 text. None for anything else, which cannot be attached.
+pub fn media_type(data: &[u8]) -> Option<&'static str> {
+    if let Some(media_type) = vendor::sniff_image(data) {
+        Some(media_type)
+    } else if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else if std::str::from_utf8(data).is_ok_and(|text| !text.contains('\0')) {
+        Some("text/plain; charset=utf-8")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::join_all;
+
+    #[test]
+    fn media_type_comes_from_the_content() {
+        assert_eq!(media_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(media_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(
+            media_type(b"<script>alert(1)</script>"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(media_type(b"text\0with a null"), None);
+        assert_eq!(media_type(&[0xFF, 0xFE, 0x00, 0x01]), None);
+    }
+
+    #[tokio::test]
+    async fn concurrent_uploads_stay_within_the_quota() {
+        let config = AttachmentsConfig {
+            path: std::env::temp_dir().join(format!("attachments-{}", uuid::Uuid::new_v4())),
+            ..AttachmentsConfig::default()
+        };
+        let store = AttachmentStore::open(&config).unwrap();
+        let save = |user: &str, n: u8| {
+            store.save(
+                Some(user.to_string()),
+                None,
+                String::from("text/plain"),
+                vec![b'a' + n; 10],
+                35,
+            )
+        };
+        let saved = join_all((0..8).map(|n| save("alice", n))).await;
+        let (ok, failed): (Vec<_>, Vec<_>) = saved.into_iter().partition(Result::is_ok);
+        assert_eq!(ok.len(), 3);
+        for err in failed.into_iter().map(Result::unwrap_err) {
+            let err = err.downcast::<QuotaExceeded>().unwrap();
+            assert_eq!(err.used, 30);
+        }
+        // Quotas are kept per user
+        assert!(save("bob", 0).await.is_ok());
+
+        let attachment = ok.into_iter().next().unwrap().unwrap();
+        assert!(store.get_for(&attachment.id, Some("alice")).is_some());
+        assert!(store.get_for(&attachment.id, Some("bob")).is_none());
+        assert!(store.get_for(&attachment.id, None).is_none());
+        std::fs::remove_dir_all(&config.path).unwrap();
+    }
+}
@@ -0,0 +1,891 @@
+use anyhow::{anyhow, bail, Context, Result};
+use arc_swap::ArcSwap;
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::Filter;
+
+mod persona;
+pub mod watcher;
+
+pub use persona::{Persona, PersonasConfig};
+
+const CONFIG_PATH: &str = "CONFIG_PATH";
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const DEFAULT_OPENAI_MODEL: &str = "o1-preview";
+const DEFAULT_CLAUDE_MODEL: &str = "claude-3-5-sonnet-latest";
+const DEFAULT_OPENAI_URL: &str = "https://api.openai.com";
+const DEFAULT_CLAUDE_URL: &str = "https://api.anthropic.com";
+const DEFAULT_STOCK_URL: &str = "https://api.jarvis-stock.tw";
+const MIN_TOKEN_LENGTH: usize = 32;
+
+/// Current configuration, swapped atomically on reload
+pub type SharedConfig = Arc<ArcSwap<Config>>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub auth: AuthConfig,
+    pub memory: MemoryConfig,
+    pub openai: VendorConfig,
+    pub claude: VendorConfig,
+    pub images: ImagesConfig,
+    pub fetch: FetchConfig,
+    pub attachments: AttachmentsConfig,
+    pub plugins: PluginsConfig,
+    pub mcp: McpConfig,
+    pub watchlists: WatchlistsConfig,
+    pub schedules: SchedulesConfig,
+    pub webhooks: WebhooksConfig,
+    pub limits: LimitsConfig,
+    pub prompts: PromptsConfig,
+    pub personas: PersonasConfig,
+    /// File this config was loaded from
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub static_dir: PathBuf,
+    /// Maximum accepted JSON body size in bytes
+    pub body_limit: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            port: 3000,
+            static_dir: PathBuf::from("static/"),
+            body_limit: 8192,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// Number of messages kept per conversation
+    pub capacity: usize,
+    /// Conversations without a message for this long are forgotten, along
+    /// with the persona they chose
+    pub idle_hours: u64,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            capacity: 4,
+            idle_hours: 24,
+        }
+    }
+}
+
+impl MemoryConfig {
+    pub fn idle(&self) -> Duration {
+        Duration::from_secs(self.idle_hours * 60 * 60)
+    }
+}
+
+/// Users of the API and their bearer tokens. Requests without a token are
+/// anonymous.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Token of each user, sent as `Authorization: Bearer <token>`
+    pub tokens: HashMap<String, String>,
+}
+
+impl AuthConfig {
+    /// User holding `token`
+    pub fn user(&self, token: &str) -> Option<&str> {
+        // Check every token in constant time so timing tells nothing about them
+        self.tokens.iter().fold(None, |found, (user, expected)| {
+            match same_bytes(expected.as_bytes(), token.as_bytes()) {
+                true => Some(user.as_str()),
+                false => found,
+            }
+        })
+    }
+}
+
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Requests accepted per conversation each minute, 0 disables the limit
+    pub requests_per_minute: u32,
+}
+
+/// System prompts overriding the built-in ones, reloaded when the files change
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptsConfig {
+    pub openai: Option<PromptFile>,
+    pub stock: Option<PromptFile>,
+    pub watchlist: Option<PromptFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(from = "PathBuf")]
+pub struct PromptFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+impl From<PathBuf> for PromptFile {
+    fn from(path: PathBuf) -> Self {
+        PromptFile {
+            path,
+            text: String::new(),
+        }
+    }
+}
+
+impl PromptsConfig {
+    fn files_mut(&mut self) -> impl Iterator<Item = &mut PromptFile> {
+        [
+            self.openai.as_mut(),
+            self.stock.as_mut(),
+            self.watchlist.as_mut(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    fn files(&self) -> impl Iterator<Item = &PromptFile> {
+        [
+            self.openai.as_ref(),
+            self.stock.as_ref(),
+            self.watchlist.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Prompt text when a file is configured, `default` otherwise
+    pub fn resolve<'a>(prompt: &'a Option<PromptFile>, default: &'a str) -> &'a str {
+        prompt
+            .as_ref()
+            .map_or(default, |prompt| prompt.text.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VendorConfig {
+    pub api_key: String,
+    /// API origin, e.g. to go through a proxy
+    pub base_url: String,
+    pub model: String,
+    pub max_tokens: i32,
+    pub timeout_secs: u64,
+}
+
+impl VendorConfig {
+    /// Whether an API key is set, vendors without one take no requests
+    pub fn configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+impl Default for VendorConfig {
+    fn default() -> Self {
+        VendorConfig {
+            api_key: String::new(),
+            base_url: String::new(),
+            model: String::new(),
+            max_tokens: 1024 * 4,
+            timeout_secs: 60 * 10,
+        }
+    }
+}
+
+/// Images attached to messages, checked and resized before reaching a vendor
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImagesConfig {
+    /// Largest image accepted, in bytes, before any resizing
+    pub max_bytes: usize,
+    /// Longest side in pixels, larger images are downscaled to it
+    pub max_dimension: u32,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            max_bytes: 20 * 1024 * 1024,
+            max_dimension: 1568,
+        }
+    }
+}
+
+/// Downloads of URLs supplied by clients, such as attached images
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchConfig {
+    /// Time allowed for a whole download, redirects included
+    pub timeout_secs: u64,
+    pub connect_timeout_secs: u64,
+    pub max_redirects: usize,
+    /// Hosts fetched even when they resolve to private or loopback addresses
+    pub allow_hosts: Vec<String>,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        FetchConfig {
+            timeout_secs: 20,
+            connect_timeout_secs: 5,
+            max_redirects: 3,
+            allow_hosts: Vec::new(),
+        }
+    }
+}
+
+/// Files uploaded through `/api/v1/attachments`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentsConfig {
+    /// Directory holding the files, stored once per content, and their metadata
+    pub path: PathBuf,
+    /// Largest file accepted
+    pub max_bytes: u64,
+    /// Bytes of attachments a user may keep at once
+    pub user_quota_bytes: u64,
+    /// Attachments not uploaded or sent for this long are deleted
+    pub retention_hours: u64,
+    /// Files a send request may attach
+    pub max_per_message: usize,
+    /// Longest text file, or text read from a PDF, sent to a model
+    pub max_text_chars: usize,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        AttachmentsConfig {
+            path: PathBuf::from("attachments"),
+            max_bytes: 20 * 1024 * 1024,
+            user_quota_bytes: 200 * 1024 * 1024,
+            retention_hours: 7 * 24,
+            max_per_message: 10,
+            max_text_chars: 200_000,
+        }
+    }
+}
+
+impl AttachmentsConfig {
+    pub fn retention(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.retention_hours as i64)
+    }
+}
+
+impl FetchConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginsConfig {
+    /// Plugins offered to the model by default
+    pub enabled: Vec<String>,
+    /// Plugins offered to a user, replacing `enabled` for that user
+    pub users: HashMap<String, Vec<String>>,
+    /// Tool call rounds allowed before giving up on a final answer
+    pub max_tool_rounds: usize,
+    pub stock: StockConfig,
+    pub sandbox: SandboxConfig,
+    pub documents: DocumentsConfig,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        PluginsConfig {
+            enabled: Vec::new(),
+            users: HashMap::new(),
+            max_tool_rounds: 5,
+            stock: StockConfig::default(),
+            sandbox: SandboxConfig::default(),
+            documents: DocumentsConfig::default(),
+        }
+    }
+}
+
+impl PluginsConfig {
+    /// Plugins available to `user`
+    pub fn available(&self, user: Option<&str>) -> &[String] {
+        user.and_then(|user| self.users.get(user))
+            .unwrap_or(&self.enabled)
+    }
+
+    fn configured(&self) -> impl Iterator<Item = &String> {
+        self.enabled.iter().chain(self.users.values().flatten())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StockConfig {
+    pub base_url: String,
+    pub user: String,
+    pub password: String,
+    pub timeout_secs: u64,
+    /// TWSE market holidays as YYYY-MM-DD, weekends are always closed
+    pub holidays: Vec<String>,
+}
+
+impl Default for StockConfig {
+    fn default() -> Self {
+        StockConfig {
+            base_url: String::new(),
+            user: String::new(),
+            password: String::new(),
+            timeout_secs: 5,
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl StockConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Limits of the `run_code` plugin
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Interpreters running the snippets
+    pub python: String,
+    pub shell: String,
+    /// Wall clock limit, the CPU limit is the same number of seconds
+    pub timeout_secs: u64,
+    /// Address space limit of the snippet process
+    pub memory_mb: u64,
+    /// Bytes kept of stdout and of stderr, also the largest file a snippet can write
+    pub max_output_bytes: usize,
+    /// Run snippets in a fresh network namespace without interfaces
+    pub isolate_network: bool,
+    /// Run snippets in a root of their own holding `read_only_paths`, their
+    /// directory and `/tmp`, rather than seeing the whole host
+    pub isolate_filesystem: bool,
+    /// Host paths visible read-only to the snippets, those missing are skipped
+    pub read_only_paths: Vec<PathBuf>,
+    /// Unprivileged uid and gid the snippets run as when the server runs as root
+    pub user_id: u32,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        SandboxConfig {
+            python: String::from("python3"),
+            shell: String::from("sh"),
+            timeout_secs: 10,
+            memory_mb: 256,
+            max_output_bytes: 16 * 1024,
+            isolate_network: true,
+            isolate_filesystem: true,
+            read_only_paths: [
+                "/usr",
+                "/bin",
+                "/sbin",
+                "/lib",
+                "/lib32",
+                "/lib64",
+                "/etc/ld.so.cache",
+                "/etc/alternatives",
+            ]
+            .map(PathBuf::from)
+            .to_vec(),
+            user_id: 65534,
+        }
+    }
+}
+
+/// Directory searched by the `search_documents` plugin
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocumentsConfig {
+    pub root: PathBuf,
+    /// File extensions indexed, `pdf` is read page by page
+    pub extensions: Vec<String>,
+    /// Lines per chunk of text files
+    pub chunk_lines: usize,
+    /// Larger files are skipped
+    pub max_file_bytes: u64,
+    /// Upper bound of the `k` a search may ask for
+    pub max_results: usize,
+}
+
+impl Default for DocumentsConfig {
+    fn default() -> Self {
+        DocumentsConfig {
+            root: PathBuf::new(),
+            extensions: [
+                "md", "markdown", "txt", "rst", "adoc", "rs", "py", "go", "js", "ts", "java", "c",
+                "h", "cpp", "sh", "toml", "yaml", "yml", "json", "sql", "html", "pdf",
+            ]
+            .map(String::from)
+            .to_vec(),
+            chunk_lines: 40,
+            max_file_bytes: 2 * 1024 * 1024,
+            max_results: 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct McpConfig {
+    /// Seconds to wait for an MCP server to answer
+    pub timeout_secs: u64,
+    pub servers: Vec<McpServerConfig>,
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        McpConfig {
+            timeout_secs: 30,
+            servers: Vec::new(),
+        }
+    }
+}
+
+impl McpConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// An MCP server reached over stdio (`command`) or streamable HTTP (`url`).
+/// Its tools are exposed as `<name>__<tool>`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct McpServerConfig {
+    pub name: String,
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// Saved stock watchlists and their report after the market closes
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchlistsConfig {
+    /// File the watchlists are saved to
+    pub path: PathBuf,
+    /// Asia/Taipei time of the daily report on trading days, HH:MM
+    pub report_time: String,
+    /// Vendor writing the report, `openai` or `claude`
+    pub vendor: String,
+    /// Stocks accepted in one watchlist
+    pub max_stocks: usize,
+}
+
+impl Default for WatchlistsConfig {
+    fn default() -> Self {
+        WatchlistsConfig {
+            path: PathBuf::from("watchlists.json"),
+            report_time: String::from("14:30"),
+            vendor: String::from("openai"),
+            max_stocks: 20,
+        }
+    }
+}
+
+impl WatchlistsConfig {
+    pub fn report_time(&self) -> Result<NaiveTime> {
+        NaiveTime::parse_from_str(&self.report_time, "%H:%M")
+            .with_context(|| format!("watchlists.report_time {:?} is not HH:MM", self.report_time))
+    }
+}
+
+/// Recurring prompts registered through `/api/v1/schedules`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulesConfig {
+    /// File the schedules and their run history are saved to
+    pub path: PathBuf,
+    /// Timezone cron expressions are read in
+    pub timezone: String,
+    /// Runs kept per schedule
+    pub history: usize,
+}
+
+impl Default for SchedulesConfig {
+    fn default() -> Self {
+        SchedulesConfig {
+            path: PathBuf::from("schedules.json"),
+            timezone: String::from("Asia/Taipei"),
+            history: 20,
+        }
+    }
+}
+
+impl SchedulesConfig {
+    pub fn timezone(&self) -> Result<Tz> {
+        self.timezone.parse().map_err(|_| {
+            anyhow!(
+                "schedules.timezone {:?} is not a known timezone",
+                self.timezone
+            )
+        })
+    }
+}
+
+/// Endpoints notified with a signed JSON POST when answers complete, tool
+/// calls fail or scheduled runs finish
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhooksConfig {
+    /// Attempts after the first one before a delivery counts as failed
+    pub retries: u32,
+    pub timeout_secs: u64,
+    /// Deliveries kept for `/api/v1/webhooks/deliveries`
+    pub log_size: usize,
+    pub endpoints: Vec<WebhookEndpoint>,
+}
+
+impl Default for WebhooksConfig {
+    fn default() -> Self {
+        WebhooksConfig {
+            retries: 3,
+            timeout_secs: 10,
+            log_size: 100,
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+impl WebhooksConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature sent in `X-Artificial-Signature`
+    pub secret: String,
+    /// Events sent to this endpoint, all of them when empty
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl WebhookEndpoint {
+    pub fn accepts(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|wanted| wanted == event)
+    }
+}
+
+impl Config {
+    /// Load the config file named by `CONFIG_PATH` (default `config.toml`),
+    /// apply environment overrides and validate the result.
+    pub fn load() -> Result<Self> {
+        let path = match std::env::var(CONFIG_PATH) {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Some(PathBuf::from(DEFAULT_CONFIG_PATH))
+            }
+            Err(_) => None,
+        };
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        config.apply_defaults();
+        config.load_prompts()?;
+        config.personas.load()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self> {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("unable to read config file {}", path.display()))?;
+        let mut config: Config = toml::from_str(&content)
+            .with_context(|| format!("invalid config file {}", path.display()))?;
+        config.source = Some(path);
+        Ok(config)
+    }
+
+    fn load_prompts(&mut self) -> Result<()> {
+        for prompt in self.prompts.files_mut() {
+            prompt.text = std::fs::read_to_string(&prompt.path)
+                .with_context(|| format!("unable to read prompt {}", prompt.path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Files whose changes trigger a reload
+    pub fn watched_files(&self) -> Vec<PathBuf> {
+        self.source
+            .iter()
+            .cloned()
+            .chain(self.prompts.files().map(|prompt| prompt.path.clone()))
+            .chain(self.personas.files())
+            .collect()
+    }
+
+    /// Error of a request to `vendor` when it has no API key
+    pub fn check_vendor(&self, vendor: &str) -> Result<()> {
+        let (configured, env) = match vendor {
+            "openai" => (self.openai.configured(), "OPENAI_API_KEY"),
+            "claude" => (self.claude.configured(), "CLAUDE_API_KEY"),
+            _ => bail!("unknown vendor {}", vendor),
+        };
+        if !configured {
+            bail!(
+                "{} is not configured, set {}.api_key or {}",
+                vendor,
+                vendor,
+                env
+            );
+        }
+        Ok(())
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        override_env("PORT", &mut self.server.port)?;
+        override_env("OPENAI_API_KEY", &mut self.openai.api_key)?;
+        override_env("OPENAI_MODEL", &mut self.openai.model)?;
+        override_env("CLAUDE_API_KEY", &mut self.claude.api_key)?;
+        override_env("CLAUDE_MODEL", &mut self.claude.model)?;
+        override_env("PLUGIN_STOCK_USER", &mut self.plugins.stock.user)?;
+        override_env("PLUGIN_STOCK_PASSWD", &mut self.plugins.stock.password)?;
+        Ok(())
+    }
+
+    fn apply_defaults(&mut self) {
+        if self.openai.model.is_empty() {
+            self.openai.model = DEFAULT_OPENAI_MODEL.to_string();
+        }
+        if self.claude.model.is_empty() {
+            self.claude.model = DEFAULT_CLAUDE_MODEL.to_string();
+        }
+        if self.openai.base_url.is_empty() {
+            self.openai.base_url = DEFAULT_OPENAI_URL.to_string();
+        }
+        if self.claude.base_url.is_empty() {
+            self.claude.base_url = DEFAULT_CLAUDE_URL.to_string();
+        }
+        if self.plugins.stock.base_url.is_empty() {
+            self.plugins.stock.base_url = DEFAULT_STOCK_URL.to_string();
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.server.port == 0 {
+            bail!("server.port must be greater than 0");
+        }
+        if !self.server.static_dir.is_dir() {
+            bail!(
+                "server.static_dir {} is not a directory",
+                self.server.static_dir.display()
+            );
+        }
+        if self.server.body_limit == 0 {
+            bail!("server.body_limit must be greater than 0");
+        }
+        validate_auth(&self.auth)?;
+        if self.memory.capacity < 2 {
+            bail!("memory.capacity must be at least 2 to hold a question and its answer");
+        }
+        if self.memory.idle_hours == 0 {
+            bail!("memory.idle_hours must be greater than 0");
+        }
+        if !self.openai.configured() && !self.claude.configured() {
+            bail!("no vendor is configured, set openai.api_key (OPENAI_API_KEY) or claude.api_key (CLAUDE_API_KEY)");
+        }
+        validate_vendor("openai", &self.openai)?;
+        validate_vendor("claude", &self.claude)?;
+        if self.images.max_bytes == 0 || self.images.max_dimension == 0 {
+            bail!("images.max_bytes and images.max_dimension must be greater than 0");
+        }
+        if self.fetch.timeout_secs == 0 || self.fetch.connect_timeout_secs == 0 {
+            bail!("fetch.timeout_secs and fetch.connect_timeout_secs must be greater than 0");
+        }
+        if self.attachments.max_bytes == 0
+            || self.attachments.user_quota_bytes < self.attachments.max_bytes
+        {
+            bail!("attachments.max_bytes must be greater than 0 and at most attachments.user_quota_bytes");
+        }
+        if self.attachments.retention_hours == 0
+            || self.attachments.max_per_message == 0
+            || self.attachments.max_text_chars == 0
+        {
+            bail!("attachments.retention_hours, attachments.max_per_message and attachments.max_text_chars must be greater than 0");
+        }
+        if self.plugins.max_tool_rounds == 0 {
+            bail!("plugins.max_tool_rounds must be greater than 0");
+        }
+        validate_mcp(&self.mcp)?;
+        validate_watchlists(&self.watchlists)?;
+        self.schedules.timezone()?;
+        if self.schedules.history == 0 {
+            bail!("schedules.history must be greater than 0");
+        }
+        validate_webhooks(&self.webhooks)?;
+        for name in self.plugins.configured() {
+            crate::vendor::check_plugin(name, self)?;
+        }
+        self.personas.validate(self)?;
+        Ok(())
+    }
+}
+
+fn validate_auth(auth: &AuthConfig) -> Result<()> {
+    let mut tokens = HashSet::new();
+    for (user, token) in &auth.tokens {
+        if user.is_empty() {
+            bail!("auth.tokens has an empty user name");
+        }
+        if token.len() < MIN_TOKEN_LENGTH {
+            bail!(
+                "auth.tokens.{} must be at least {} characters",
+                user,
+                MIN_TOKEN_LENGTH
+            );
+        }
+        if !tokens.insert(token) {
+            bail!("auth.tokens.{} is the token of another user", user);
+        }
+    }
+    Ok(())
+}
+
+fn validate_mcp(mcp: &McpConfig) -> Result<()> {
+    if mcp.timeout_secs == 0 {
+        bail!("mcp.timeout_secs must be greater than 0");
+    }
+    let mut names = HashSet::new();
+    for server in &mcp.servers {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if server.name.is_empty() || !server.name.chars().all(valid) {
+            bail!(
+                "mcp server name {:?} must be non-empty letters, digits, '-' or '_'",
+                server.name
+            );
+        }
+        if !names.insert(server.name.as_str()) {
+            bail!("mcp server {} is configured twice", server.name);
+        }
+        if server.command.is_some() == server.url.is_some() {
+            bail!(
+                "mcp server {} needs exactly one of command or url",
+                server.name
+            );
+        }
+    }
+    Ok(())
+}
+
+fn validate_watchlists(watchlists: &WatchlistsConfig) -> Result<()> {
+    watchlists.report_time()?;
+    if !matches!(watchlists.vendor.as_str(), "openai" | "claude") {
+        bail!(
+            "watchlists.vendor must be openai or claude, got {:?}",
+            watchlists.vendor
+        );
+    }
+    if watchlists.max_stocks == 0 {
+        bail!("watchlists.max_stocks must be greater than 0");
+    }
+    Ok(())
+}
+
+fn validate_webhooks(webhooks: &WebhooksConfig) -> Result<()> {
+    if webhooks.timeout_secs == 0 {
+        bail!("webhooks.timeout_secs must be greater than 0");
+    }
+    if webhooks.log_size == 0 {
+        bail!("webhooks.log_size must be greater than 0");
+    }
+    for endpoint in &webhooks.endpoints {
+        if !endpoint.url.starts_with("http://") && !endpoint.url.starts_with("https://") {
+            bail!(
+                "webhook url {:?} must be an http or https URL",
+                endpoint.url
+            );
+        }
+        if endpoint.secret.is_empty() {
+            bail!("webhook {} needs a secret to sign deliveries", endpoint.url);
+        }
+        if let Some(event) = endpoint
+            .events
+            .iter()
+            .find(|event| !crate::webhook::EVENTS.contains(&event.as_str()))
+        {
+            bail!(
+                "webhook {} has unknown event {:?}, expected one of {}",
+                endpoint.url,
+                event,
+                crate::webhook::EVENTS.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn validate_vendor(name: &str, vendor: &VendorConfig) -> Result<()> {
+    if vendor.model.is_empty() {
+        bail!("{}.model must not be empty", name);
+    }
+    if vendor.max_tokens <= 0 {
+        bail!("{}.max_tokens must be greater than 0", name);
+    }
+    if vendor.timeout_secs == 0 {
+        bail!("{}.timeout_secs must be greater than 0", name);
+    }
+    Ok(())
+}
+
+/// Replace `target` with the parsed value of `key` when the variable is set
+fn override_env<T>(key: &str, target: &mut T) -> Result<()>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    if let Ok(value) = std::env::var(key) {
+        *target = value
+            .parse()
+            .map_err(|err| anyhow!("invalid value for {}: {}", key, err))?;
+    }
+    Ok(())
+}
+
+pub fn with_config(
+    config: SharedConfig,
+) -> impl Filter<Extract = (SharedConfig,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || config.clone())
+}
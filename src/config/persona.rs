@@ -0,0 +1,346 @@
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use chrono_tz::Asia::Taipei;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::Config;
+
+/// Variables a persona prompt may use as `{{name}}`
+const VARIABLES: [&str; 5] = ["date", "weekday", "time", "user", "persona"];
+
+/// Named system prompts with their own models, temperature and tools, one
+/// `<name>.toml` file each in `dir`, reloaded when the files change
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PersonasConfig {
+    pub dir: PathBuf,
+    /// Persona of conversations that chose none, the built-in prompts when unset
+    pub default: Option<String>,
+    #[serde(skip)]
+    pub loaded: BTreeMap<String, Arc<Persona>>,
+}
+
+impl Default for PersonasConfig {
+    fn default() -> Self {
+        PersonasConfig {
+            dir: PathBuf::from("personas"),
+            default: None,
+            loaded: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Persona {
+    #[serde(skip_deserializing)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// System prompt, see `render` for its variables
+    #[serde(skip_serializing)]
+    pub prompt: String,
+    /// Models replacing the configured ones, per vendor
+    #[serde(default)]
+    pub models: PersonaModels,
+    /// A number for both vendors, or a table with one per vendor
+    #[serde(default, deserialize_with = "temperature")]
+    pub temperature: PersonaTemperature,
+    /// Tools offered, narrowing those the user may use
+    pub tools: Option<Vec<String>>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PersonaModels {
+    pub openai: Option<String>,
+    pub claude: Option<String>,
+}
+
+/// Sampling temperature per vendor. OpenAI takes 0 to 2, Claude 0 to 1.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PersonaTemperature {
+    pub openai: Option<f64>,
+    pub claude: Option<f64>,
+}
+
+fn temperature<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PersonaTemperature, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Temperature {
+        Both(f64),
+        PerVendor(PersonaTemperature),
+    }
+
+    Ok(match Temperature::deserialize(deserializer)? {
+        Temperature::Both(temperature) => PersonaTemperature {
+            openai: Some(temperature),
+            claude: Some(temperature),
+        },
+        Temperature::PerVendor(temperature) => temperature,
+    })
+}
+
+impl PersonasConfig {
+    /// Read every `*.toml` file in `dir`, none when the directory is missing
+    pub(super) fn load(&mut self) -> Result<()> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("unable to read personas {}", self.dir.display()))
+            }
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("unable to read persona {}", path.display()))?;
+            let mut persona: Persona = toml::from_str(&content)
+                .with_context(|| format!("invalid persona {}", path.display()))?;
+            persona.name = name.to_string();
+            persona.path = path.clone();
+            self.loaded.insert(persona.name.clone(), Arc::new(persona));
+        }
+        Ok(())
+    }
+
+    /// The directory, so added and removed files are noticed, and every file
+    pub(super) fn files(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        std::iter::once(self.dir.clone())
+            .chain(self.loaded.values().map(|persona| persona.path.clone()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.loaded.contains_key(name)
+    }
+
+    /// The persona a conversation `selected`, the default one when it chose
+    /// none or its choice is gone
+    pub fn select(&self, selected: Option<&str>) -> Option<Arc<Persona>> {
+        selected
+            .and_then(|name| self.loaded.get(name))
+            .or_else(|| {
+                self.default
+                    .as_deref()
+                    .and_then(|name| self.loaded.get(name))
+            })
+            .cloned()
+    }
+
+    pub(super) fn validate(&self, config: &Config) -> Result<()> {
+        if let Some(default) = &self.default {
+            if !self.contains(default) {
+                bail!(
+                    "personas.default {:?} has no file in {}",
+                    default,
+                    self.dir.display()
+                );
+            }
+        }
+        for persona in self.loaded.values() {
+            let name = &persona.name;
+            if persona.prompt.trim().is_empty() {
+                bail!("persona {} has an empty prompt", name);
+            }
+            let temperature = persona.temperature;
+            for (vendor, temperature, max) in [
+                ("openai", temperature.openai, 2.0),
+                ("claude", temperature.claude, 1.0),
+            ] {
+                if temperature.is_some_and(|temperature| !(0.0..=max).contains(&temperature)) {
+                    bail!(
+                        "persona {} {} temperature must be between 0 and {}",
+                        name,
+                        vendor,
+                        max
+                    );
+                }
+            }
+            if [&persona.models.openai, &persona.models.claude]
+                .into_iter()
+                .flatten()
+                .any(|model| model.is_empty())
+            {
+                bail!("persona {} models must not be empty", name);
+            }
+            for tool in persona.tools.iter().flatten() {
+                crate::vendor::check_plugin(tool, config)
+                    .with_context(|| format!("persona {}", name))?;
+            }
+            let mut unknown = Vec::new();
+            substitute(&persona.prompt, |variable| {
+                if !VARIABLES.contains(&variable) {
+                    unknown.push(variable.to_string());
+                }
+                None
+            });
+            if let Some(variable) = unknown.first() {
+                bail!(
+                    "persona {} uses unknown variable {{{{{}}}}}, known are {}",
+                    name,
+                    variable,
+                    VARIABLES.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Persona {
+    /// The prompt with `{{date}}`, `{{weekday}}` and `{{time}}` in Taipei,
+    /// `{{user}}` and `{{persona}}` filled in
+    pub fn render(&self, user: Option<&str>) -> String {
+        let now = Utc::now().with_timezone(&Taipei);
+        substitute(&self.prompt, |variable| match variable {
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "weekday" => Some(now.format("%A").to_string()),
+            "time" => Some(now.format("%H:%M").to_string()),
+            "user" => Some(user.unwrap_or("the user").to_string()),
+            "persona" => Some(self.name.clone()),
+            _ => None,
+        })
+    }
+
+    /// Tools of a request under this persona that asked for `requested`
+    pub fn tools_for(&self, requested: Option<&[String]>) -> Option<Vec<String>> {
+        match (&self.tools, requested) {
+            (Some(tools), Some(requested)) => Some(
+                tools
+                    .iter()
+                    .filter(|tool| requested.contains(tool))
+                    .cloned()
+                    .collect(),
+            ),
+            (Some(tools), None) => Some(tools.clone()),
+            (None, requested) => requested.map(<[String]>::to_vec),
+        }
+    }
+}
+
+/// `template` with every `{{name}}` replaced by `value(name)`, or kept when
+/// that is None
+fn substitute(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + length + 4;
+        rendered.push_str(&rest[..start]);
+        match value(rest[start + 2..end - 2].trim()) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persona(prompt: &str, tools: Option<&[&str]>) -> Persona {
+        Persona {
+            name: String::from("analyst"),
+            description: String::new(),
+            prompt: prompt.to_string(),
+            models: PersonaModels::default(),
+            temperature: PersonaTemperature::default(),
+            tools: tools.map(|tools| tools.iter().map(|tool| tool.to_string()).collect()),
+            path: PathBuf::new(),
+        }
+    }
+
+    fn upper(name: &str) -> Option<String> {
+        (name != "unknown").then(|| name.to_uppercase())
+    }
+
+    #[test]
+    fn substitutes_known_variables() {
+        assert_eq!(substitute("Hi {{user}}!", upper), "Hi USER!");
+        assert_eq!(substitute("{{ user }}{{date}}", upper), "USERDATE");
+        assert_eq!(substitute("no variables", upper), "no variables");
+        assert_eq!(substitute("", upper), "");
+    }
+
+    #[test]
+    fn keeps_unknown_and_unclosed_variables() {
+        assert_eq!(
+            substitute("{{unknown}} {{user}}", upper),
+            "{{unknown}} USER"
+        );
+        assert_eq!(substitute("{{user", upper), "{{user");
+        assert_eq!(substitute("a }} {{user}} {{", upper), "a }} USER {{");
+    }
+
+    #[test]
+    fn does_not_substitute_values_again() {
+        let value = |_: &str| Some(String::from("{{user}}"));
+        assert_eq!(
+            substitute("{{a}} and {{b}}", value),
+            "{{user}} and {{user}}"
+        );
+    }
+
+    #[test]
+    fn renders_user_and_persona() {
+        let persona = persona("{{persona}} for {{user}} on {{weekday}}", None);
+        let rendered = persona.render(Some("alice"));
+        assert!(
+            rendered.starts_with("analyst for alice on "),
+            "{}",
+            rendered
+        );
+        assert!(!rendered.contains("{{"));
+        assert!(persona.render(None).contains("for the user"));
+    }
+
+    #[test]
+    fn narrows_requested_tools() {
+        let requested = [String::from("run_code"), String::from("get_stock_quotes")];
+        let narrowed = persona("p", Some(&["get_stock_quotes", "search_documents"]));
+        assert_eq!(
+            narrowed.tools_for(Some(&requested)),
+            Some(vec![String::from("get_stock_quotes")])
+        );
+        assert_eq!(
+            narrowed.tools_for(None),
+            Some(vec![
+                String::from("get_stock_quotes"),
+                String::from("search_documents")
+            ])
+        );
+        let open = persona("p", None);
+        assert_eq!(open.tools_for(Some(&requested)), Some(requested.to_vec()));
+        assert_eq!(open.tools_for(None), None);
+    }
+
+    #[test]
+    fn temperature_is_shared_or_per_vendor() {
+        let temperature = |toml: &str| toml::from_str::<Persona>(toml).map(|p| p.temperature);
+        let shared = temperature("prompt = \"p\"\ntemperature = 1").unwrap();
+        assert_eq!((shared.openai, shared.claude), (Some(1.0), Some(1.0)));
+        let split = temperature("prompt = \"p\"\n[temperature]\nopenai = 1.5").unwrap();
+        assert_eq!((split.openai, split.claude), (Some(1.5), None));
+        let unset = temperature("prompt = \"p\"").unwrap();
+        assert_eq!((unset.openai, unset.claude), (None, None));
+        assert!(temperature("prompt = \"p\"\n[temperature]\ngemini = 1").is_err());
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use super::{Config, SharedConfig};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Poll the config file and prompt files, swapping in a freshly validated
+/// config when any of them changes. An invalid file keeps the current config.
+pub fn watch(config: SharedConfig) {
+    tokio::spawn(async move {
+        let mut stamps = modified(&config).await;
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if modified(&config).await == stamps {
+                continue;
+            }
+
+            match tokio::task::spawn_blocking(Config::load).await {
+                Ok(Ok(next)) => {
+                    warn_restart_required(&config.load(), &next);
+                    config.store(Arc::new(next));
+                    tracing::info!("configuration reloaded");
+                }
+                Ok(Err(err)) => {
+                    tracing::error!("configuration reload failed, keeping current: {:#}", err)
+                }
+                Err(err) => {
+                    tracing::error!("configuration reload failed, keeping current: {}", err)
+                }
+            }
+            stamps = modified(&config).await;
+        }
+    });
+}
+
+/// Modification times of the watched files, read off the runtime threads
+async fn modified(config: &SharedConfig) -> Vec<Option<SystemTime>> {
+    let files = config.load().watched_files();
+    tokio::task::spawn_blocking(move || stamps(&files))
+        .await
+        .unwrap_or_default()
+}
+
+fn stamps(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|path| {
+            std::fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+        })
+        .collect()
+}
+
+fn warn_restart_required(current: &Config, next: &Config) {
+    if current.server.port != next.server.port
+        || current.server.static_dir != next.server.static_dir
+        || current.memory.capacity != next.memory.capacity
+        || current.memory.idle_hours != next.memory.idle_hours
+        || current.mcp.servers != next.mcp.servers
+        || current.watchlists.path != next.watchlists.path
+        || current.schedules.path != next.schedules.path
+        || current.attachments.path != next.attachments.path
+    {
+        tracing::warn!(
+            "server.port, server.static_dir, memory.capacity, memory.idle_hours, mcp.servers, watchlists.path, schedules.path and attachments.path only apply after a restart"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Timelike};
+use std::str::FromStr;
+
+/// Occurrences searched for before an expression counts as never firing,
+/// e.g. `0 0 31 2 *`
+const MAX_YEARS: i64 = 5;
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A five field cron expression, `minute hour day-of-month month day-of-week`,
+/// with `*`, lists, ranges, steps, month and weekday names, and the
+/// `@hourly`, `@daily`, `@weekly` and `@monthly` shorthands
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Either day field matches when both are restricted, as in classic cron
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            bail!(
+                "cron expression {:?} needs 5 fields: minute hour day-of-month month day-of-week",
+                expression
+            );
+        }
+
+        let mut weekdays = field(fields[4], 0, 7, &WEEKDAYS)?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: field(fields[0], 0, 59, &[])?,
+            hours: field(fields[1], 0, 23, &[])?,
+            days: field(fields[2], 1, 31, &[])?,
+            months: field(fields[3], 1, 12, &MONTHS)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+}
+
+impl Cron {
+    /// First occurrence strictly after `after`, in the timezone of `after`
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let end = start + Duration::days(366 * MAX_YEARS);
+        let mut time = start;
+        while time < end {
+            if !bit(self.months, time.month()) {
+                time = next_month(time)?;
+            } else if !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !bit(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if !bit(self.minutes, time.minute()) {
+                time += Duration::minutes(1);
+            } else {
+                // Local times skipped by a DST change never happen
+                match timezone.from_local_datetime(&time).earliest() {
+                    Some(occurrence) => return Some(occurrence),
+                    None => time += Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+fn bit(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+fn next_month(time: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (year, month) = match time.month() {
+        12 => (time.year() + 1, 1),
+        month => (time.year(), month + 1),
+    };
+    time.date()
+        .with_day(1)?
+        .with_month(month)?
+        .with_year(year)?
+        .and_hms_opt(0, 0, 0)
+}
+
+/// Bit set of the values a field allows
+fn field(text: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut set = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| anyhow!("invalid step in cron field {:?}", text))?;
+                if step == 0 {
+                    bail!("cron step must be greater than 0 in {:?}", text);
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (value(first, names, min)?, value(last, names, min)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (value(range, names, min)?, max),
+                None => {
+                    let value = value(range, names, min)?;
+                    (value, value)
+                }
+            },
+        };
+        if first < min || last > max || first > last {
+            bail!(
+                "cron field {:?} is outside {}-{} or reversed",
+                text,
+                min,
+                max
+            );
+        }
+        for value in (first..=last).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn value(text: &str, names: &[&str], min: u32) -> Result<u32> {
+    let lower = text.to_lowercase();
+    if let Some(index) = names.iter().position(|name| *name == lower) {
+        return Ok(index as u32 + min);
+    }
+    text.parse()
+        .map_err(|_| anyhow!("invalid cron value {:?}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use chrono_tz::America::New_York;
+
+    fn next(expression: &str, after: &str) -> Option<String> {
+        let cron: Cron = expression.parse().unwrap();
+        let after =
+            Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(after, "%Y-%m-%d %H:%M").unwrap());
+        cron.next_after(&after)
+            .map(|next| next.format("%Y-%m-%d %H:%M %a").to_string())
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expression in [
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "*/x * * * *",
+            "* * * foo *",
+            "",
+        ] {
+            assert!(expression.parse::<Cron>().is_err(), "{:?}", expression);
+        }
+    }
+
+    #[test]
+    fn parses_names_shorthands_and_sunday_as_seven() {
+        let parse = |expression: &str| expression.parse::<Cron>().unwrap();
+        assert_eq!(parse("@daily"), parse("0 0 * * *"));
+        assert_eq!(parse("@weekly"), parse("0 0 * * 7"));
+        assert_eq!(parse("0 9 * JAN-mar Mon-Fri"), parse("0 9 * 1-3 1-5"));
+        assert_eq!(parse("0,30 * * * *"), parse("*/30 * * * *"));
+        assert_eq!(parse("5/20 * * * *"), parse("5,25,45 * * * *"));
+    }
+
+    #[test]
+    fn finds_the_next_occurrence() {
+        let next = |expression| next(expression, "2026-10-19 10:07");
+        assert_eq!(next("*/15 * * * *").unwrap(), "2026-10-19 10:15 Mon");
+        assert_eq!(next("0 9 * * *").unwrap(), "2026-10-20 09:00 Tue");
+        assert_eq!(next("0 9 1 * *").unwrap(), "2026-11-01 09:00 Sun");
+        assert_eq!(next("0 0 1 jan *").unwrap(), "2027-01-01 00:00 Fri");
+        assert_eq!(next("30 8 * * sat,sun").unwrap(), "2026-10-24 08:30 Sat");
+    }
+
+    #[test]
+    fn is_strictly_after() {
+        assert_eq!(
+            next("0 9 * * *", "2026-10-19 09:00").unwrap(),
+            "2026-10-20 09:00 Tue"
+        );
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // Friday the 13th cron fires on every 13th and every Friday
+        assert_eq!(
+            next("0 0 13 * fri", "2026-10-19 10:00").unwrap(),
+            "2026-10-23 00:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 13 * fri", "2026-11-10 10:00").unwrap(),
+            "2026-11-13 00:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 13 * mon", "2026-11-10 10:00").unwrap(),
+            "2026-11-13 00:00 Fri"
+        );
+        // With one day field unrestricted, the other one alone decides
+        assert_eq!(
+            next("0 0 13 * *", "2026-10-19 10:00").unwrap(),
+            "2026-11-13 00:00 Fri"
+        );
+        assert_eq!(
+            next("0 0 * * fri", "2026-10-19 10:00").unwrap(),
+            "2026-10-23 00:00 Fri"
+        );
+    }
+
+    #[test]
+    fn handles_leap_days_and_impossible_dates() {
+        assert_eq!(
+            next("0 0 29 feb *", "2026-10-19 10:00").unwrap(),
+            "2028-02-29 00:00 Tue"
+        );
+        assert_eq!(next("0 0 31 2 *", "2026-10-19 10:00"), None);
+        assert_eq!(next("0 0 31 4,6,9,11 *", "2026-10-19 10:00"), None);
+    }
+
+    #[test]
+    fn skips_local_times_missing_on_dst_changes() {
+        let cron: Cron = "30 2 * * *".parse().unwrap();
+        let after = New_York.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap();
+        let next = cron.next_after(&after).unwrap();
+        assert_eq!(next.to_string(), "2026-03-09 02:30:00 EDT");
+    }
+}
@@ -1,19 +1,40 @@
 use dashmap::DashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use warp::Filter;
 
 use super::queue::FixedSizeQueue;
 
+/// How often idle conversations are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
 pub type Memory = Arc<MemoryEmitter>;
 
+/// What is kept of a conversation
+struct Conversation {
+    messages: FixedSizeQueue<String>,
+    /// Persona the conversation chose
+    persona: Option<String>,
+    /// Last message or persona choice
+    used_at: Instant,
+}
+
 pub struct MemoryEmitter {
-    inner: DashMap<String, FixedSizeQueue<String>>,
+    inner: DashMap<String, Conversation>,
+    capacity: usize,
+    /// Conversations idle this long are forgotten along with their persona
+    idle: Duration,
+    /// Last time idle conversations were dropped
+    swept: Mutex<Instant>,
 }
 
 impl MemoryEmitter {
-    pub fn new() -> Self {
+    pub fn new(capacity: usize, idle: Duration) -> Self {
         MemoryEmitter {
             inner: DashMap::new(),
+            capacity,
+            idle,
+            swept: Mutex::new(Instant::now()),
         }
     }
 
@@ -21,7 +42,24 @@ impl MemoryEmitter {
     pub fn get(&self, uuid: &str) -> String {
         self.inner
             .get(uuid)
-            .map_or_else(String::new, |mem_store| mem_store.compose())
+            .map_or_else(String::new, |conversation| conversation.messages.compose())
+    }
+
+    pub fn persona(&self, uuid: &str) -> Option<String> {
+        self.inner
+            .get(uuid)
+            .and_then(|conversation| conversation.persona.clone())
+    }
+
+    /// Answer `uuid` as `persona` from now on, as the default one when empty
+    pub fn choose_persona(&self, uuid: &str, persona: &str) {
+        self.conversation(uuid.to_string()).persona =
+            Some(persona.to_string()).filter(|persona| !persona.is_empty());
+    }
+
+    /// Number of conversations currently held
+    pub fn len(&self) -> usize {
+        self.inner.len()
     }
 
     pub fn insert(&self, uuid: Arc<String>, reply: Arc<String>) {
@@ -46,13 +84,37 @@ impl MemoryEmitter {
                 Arc::as_ref(&arc).clone()
             }
         };
-        let mut entry = self.inner.entry(uuid).or_insert(FixedSizeQueue::new(4));
-        entry.handle_incoming(reply);
+        self.conversation(uuid).messages.handle_incoming(reply);
+    }
+
+    /// The conversation `uuid`, created when missing and marked used
+    fn conversation(&self, uuid: String) -> dashmap::mapref::one::RefMut<'_, String, Conversation> {
+        let now = Instant::now();
+        self.sweep(now);
+        let mut conversation = self.inner.entry(uuid).or_insert_with(|| Conversation {
+            messages: FixedSizeQueue::new(self.capacity),
+            persona: None,
+            used_at: now,
+        });
+        conversation.used_at = now;
+        conversation
+    }
+
+    /// Forget the conversations idle for longer than `idle`, at most once per
+    /// sweep interval
+    fn sweep(&self, now: Instant) {
+        let mut swept = self.swept.lock().unwrap_or_else(|err| err.into_inner());
+        if now.duration_since(*swept) < SWEEP_INTERVAL {
+            return;
+        }
+        *swept = now;
+        self.inner
+            .retain(|_, conversation| now.duration_since(conversation.used_at) < self.idle);
     }
 }
 
-pub fn create_memory() -> Memory {
-    Arc::new(MemoryEmitter::new())
+pub fn create_memory(capacity: usize, idle: Duration) -> Memory {
+    Arc::new(MemoryEmitter::new(capacity, idle))
 }
 
 pub fn with_memory(
@@ -23,6 +23,12 @@ impl SseEmitter {
     pub fn insert(&mut self, uuid: Arc<String>, tx: mpsc::UnboundedSender<Message>) {
         self.inner.insert(uuid.as_str().to_owned(), tx);
     }
+
+    /// Drop senders whose clients have disconnected and return the remaining count
+    pub fn active(&mut self) -> usize {
+        self.inner.retain(|_, tx| !tx.is_closed());
+        self.inner.len()
+    }
 }
 
 impl Deref for SseEmitter {
@@ -49,10 +55,20 @@ pub fn with_sse(
     warp::any().map(move || sse.clone())
 }
 
+#[tracing::instrument(
+    name = "sse_publish",
+    level = "debug",
+    skip_all,
+    fields(conversation_id = %uuid)
+)]
 pub async fn publish(sse: Sse, uuid: Arc<String>, message: Message) {
     let sse = sse.lock().await;
     match sse.get(uuid.as_str()) {
-        Some(tx) => tx.send(message.clone()).unwrap(),
-        None => println!("No tx found for {}", uuid),
+        Some(tx) => {
+            if tx.send(message).is_err() {
+                tracing::warn!(conversation_id = %uuid, "sse client disconnected");
+            }
+        }
+        None => tracing::warn!(conversation_id = %uuid, "no sse client found"),
     }
 }
@@ -0,0 +1,253 @@
+use anyhow::Result;
+use bytes::Buf;
+use futures::TryStreamExt;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::filters::multipart::{FormData, Part};
+use warp::http::{header, StatusCode};
+use warp::reply::{with_status, Reply, Response};
+use warp::Filter;
+
+use crate::api::attachment::Attachment;
+use crate::attachments::{self, AttachmentStore, Attachments, QuotaExceeded};
+use crate::config::SharedConfig;
+use crate::routes::auth::Identity;
+
+/// How often expired attachments and unreferenced files are deleted
+const COLLECT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A file read from an upload form
+struct Upload {
+    filename: Option<String>,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+/// Store an upload owned by the token's user, or an anonymous one
+pub async fn upload(
+    form: FormData,
+    user: Identity,
+    attachments: Attachments,
+    config: SharedConfig,
+) -> Result<Response, warp::Rejection> {
+    let (max_bytes, quota) = {
+        let config = config.load();
+        (
+            config.attachments.max_bytes,
+            config.attachments.user_quota_bytes,
+        )
+    };
+    let upload = match read_form(form, max_bytes).await {
+        Ok(upload) => upload,
+        Err((status, message)) => return Ok(error(status, &message)),
+    };
+    let saved = attachments
+        .save(
+            user.map(|user| user.to_string()),
+            upload.filename,
+            upload.media_type.to_string(),
+            upload.data,
+            quota,
+        )
+        .await;
+    match saved {
+        Ok(attachment) => {
+            tracing::info!(
+                attachment = %attachment.id,
+                size = attachment.size,
+                media_type = %attachment.media_type,
+                "attachment uploaded"
+            );
+            Ok(with_status(warp::reply::json(&attachment), StatusCode::CREATED).into_response())
+        }
+        Err(err) if err.is::<QuotaExceeded>() => Ok(error(StatusCode::FORBIDDEN, &err.to_string())),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to save attachment");
+            Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to save attachment",
+            ))
+        }
+    }
+}
+
+/// Attachments of the token's user
+pub async fn list(
+    user: Arc<String>,
+    attachments: Attachments,
+) -> Result<Response, warp::Rejection> {
+    let found: Vec<Attachment> = attachments
+        .list()
+        .into_iter()
+        .filter(|attachment| attachment.user.as_deref() == Some(user.as_str()))
+        .collect();
+    Ok(warp::reply::json(&found).into_response())
+}
+
+pub async fn get(
+    id: String,
+    user: Identity,
+    attachments: Attachments,
+) -> Result<Response, warp::Rejection> {
+    match available(&attachments, &id, &user) {
+        Ok(attachment) => Ok(warp::reply::json(&attachment).into_response()),
+        Err((status, message)) => Ok(error(status, message)),
+    }
+}
+
+/// The uploaded file itself, as a download. The media type comes from the
+/// content rather than the upload, so a page uploaded as text/html is served
+/// as plain text and never runs on this origin.
+pub async fn content(
+    id: String,
+    user: Identity,
+    attachments: Attachments,
+) -> Result<Response, warp::Rejection> {
+    let attachment = match available(&attachments, &id, &user) {
+        Ok(attachment) => attachment,
+        Err((status, message)) => return Ok(error(status, message)),
+    };
+    match attachments.read(&attachment).await {
+        Ok(data) => {
+            let media_type = attachments::media_type(&data).unwrap_or("application/octet-stream");
+            let reply = warp::reply::with_header(data, header::CONTENT_TYPE, media_type);
+            let reply = warp::reply::with_header(reply, header::CONTENT_DISPOSITION, "attachment");
+            Ok(
+                warp::reply::with_header(reply, header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+                    .into_response(),
+            )
+        }
+        Err(err) => {
+            tracing::error!(attachment = %id, error = %err, "failed to read attachment");
+            Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to read attachment",
+            ))
+        }
+    }
+}
+
+pub async fn delete(
+    id: String,
+    user: Identity,
+    attachments: Attachments,
+) -> Result<Response, warp::Rejection> {
+    if let Err((status, message)) = available(&attachments, &id, &user) {
+        return Ok(error(status, message));
+    }
+    match attachments.remove(&id).await {
+        Ok(Some(_)) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(None) => Ok(error(StatusCode::NOT_FOUND, "attachment not found")),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to delete attachment");
+            Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to delete attachment",
+            ))
+        }
+    }
+}
+
+/// The attachment `id` when `user` may see it. Attachments of other users
+/// answer 404 like missing ones.
+fn available(
+    attachments: &Attachments,
+    id: &str,
+    user: &Identity,
+) -> Result<Attachment, (StatusCode, &'static str)> {
+    attachments
+        .get_for(id, user.as_deref().map(String::as_str))
+        .ok_or((StatusCode::NOT_FOUND, "attachment not found"))
+}
+
+async fn read_form(mut form: FormData, max_bytes: u64) -> Result<Upload, (StatusCode, String)> {
+    let invalid = |err: warp::Error| (StatusCode::BAD_REQUEST, format!("invalid form: {}", err));
+    let mut file = None;
+    while let Some(part) = form.try_next().await.map_err(invalid)? {
+        match part.name() {
+            "file" if file.is_none() => {
+                let filename = part.filename().map(str::to_string);
+                let data = read_part(part, max_bytes).await?.ok_or((
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("file is larger than {} bytes", max_bytes),
+                ))?;
+                file = Some((filename, data));
+            }
+            name => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("unexpected form field {:?}", name),
+                ))
+            }
+        }
+    }
+    let (filename, data) =
+        file.ok_or((StatusCode::BAD_REQUEST, String::from("file is missing")))?;
+    if data.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, String::from("file is empty")));
+    }
+    // The type the client claims is ignored, only files a message can carry
+    // are kept
+    let media_type = attachments::media_type(&data).ok_or((
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        String::from("only images, PDFs and text files can be uploaded"),
+    ))?;
+    Ok(Upload {
+        filename,
+        media_type,
+        data,
+    })
+}
+
+/// Content of `part`, or None once it grows past `max_bytes`
+async fn read_part(
+    mut part: Part,
+    max_bytes: u64,
+) -> Result<Option<Vec<u8>>, (StatusCode, String)> {
+    let mut data = Vec::new();
+    while let Some(chunk) = part.data().await {
+        let chunk =
+            chunk.map_err(|err| (StatusCode::BAD_REQUEST, format!("invalid form: {}", err)))?;
+        data.extend_from_slice(chunk.chunk());
+        if data.len() as u64 > max_bytes {
+            return Ok(None);
+        }
+    }
+    Ok(Some(data))
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    with_status(warp::reply::json(&json!({ "error": message })), status).into_response()
+}
+
+/// Open the attachment store and start deleting expired attachments
+pub fn setup_attachments(config: SharedConfig) -> Result<Attachments> {
+    let attachments: Attachments = Arc::new(AttachmentStore::open(&config.load().attachments)?);
+    tracing::info!(
+        count = attachments.list().len(),
+        path = %config.load().attachments.path.display(),
+        "attachments loaded"
+    );
+    tokio::spawn(collect(config, attachments.clone()));
+    Ok(attachments)
+}
+
+pub fn with_attachments(
+    attachments: Attachments,
+) -> impl Filter<Extract = (Attachments,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || attachments.clone())
+}
+
+async fn collect(config: SharedConfig, attachments: Attachments) {
+    let mut interval = tokio::time::interval(COLLECT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let retention = config.load().attachments.retention();
+        match attachments.collect(retention).await {
+            Ok(0) => {}
+            Ok(removed) => tracing::info!(removed, "expired attachments deleted"),
+            Err(err) => tracing::error!(error = %err, "failed to collect attachments"),
+        }
+    }
+}
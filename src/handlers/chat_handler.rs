@@ -0,0 +1,520 @@
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use reqwest_eventsource::{Event, EventSource};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tracing::Instrument;
+use warp::http::StatusCode;
+use warp::reply::{with_status, Reply, Response};
+use warp::Filter;
+
+use crate::api::chat::{ChatRequest, ChatRequestIntermediate};
+use crate::api::sse::Message;
+use crate::config::SharedConfig;
+use crate::emitter::*;
+use crate::handlers::Completion;
+use crate::limiter::Limiter;
+use crate::routes::auth::Identity;
+use crate::telemetry::metrics;
+use crate::telemetry::trace::Queued;
+use crate::vendor::{MessageAction, ToolExchange, Vendor};
+use crate::webhook;
+
+type Job = (
+    sse_emitter::Sse,
+    memory_emitter::Memory,
+    ChatRequest,
+    Queued,
+    Option<Completion>,
+);
+
+/// Queue of the requests one vendor answers
+#[derive(Clone)]
+pub struct VendorChan {
+    vendor: &'static str,
+    tx: mpsc::UnboundedSender<Job>,
+}
+
+static STOP_SIGN: Lazy<Arc<String>> = Lazy::new(|| Arc::new(String::from("[[stop]]")));
+
+pub async fn send(
+    request: ChatRequestIntermediate,
+    identity: Identity,
+    sse: sse_emitter::Sse,
+    mem: memory_emitter::Memory,
+    chan: VendorChan,
+    config: SharedConfig,
+    limiter: Limiter,
+) -> Result<Response, warp::Rejection> {
+    let config = config.load();
+    if !limiter.allow(&request.uuid, config.limits.requests_per_minute) {
+        return Ok(StatusCode::TOO_MANY_REQUESTS.into_response());
+    }
+    if let Err(err) = config.check_vendor(chan.vendor) {
+        let error = json!({ "error": err.to_string() });
+        return Ok(
+            with_status(warp::reply::json(&error), StatusCode::SERVICE_UNAVAILABLE).into_response(),
+        );
+    }
+    // The body may still name the user, but only the token's
+    if request.user.is_some() && request.user.as_deref() != identity.as_deref().map(String::as_str)
+    {
+        let error = json!({ "error": "user does not match the token" });
+        return Ok(with_status(warp::reply::json(&error), StatusCode::FORBIDDEN).into_response());
+    }
+    if let Some(persona) = &request.persona {
+        if !persona.is_empty() && !config.personas.contains(persona) {
+            let error = json!({ "error": format!("unknown persona {}", persona) });
+            return Ok(
+                with_status(warp::reply::json(&error), StatusCode::BAD_REQUEST).into_response(),
+            );
+        }
+        mem.choose_persona(&request.uuid, persona);
+    }
+
+    let request = ChatRequest {
+        user: identity,
+        ..request.into()
+    };
+    enqueue(&chan, sse, mem, request, None);
+    Ok(StatusCode::OK.into_response())
+}
+
+fn enqueue(
+    chan: &VendorChan,
+    sse: sse_emitter::Sse,
+    mem: memory_emitter::Memory,
+    request: ChatRequest,
+    done: Option<Completion>,
+) {
+    if chan
+        .tx
+        .send((sse, mem, request, Queued::enqueue(chan.vendor), done))
+        .is_ok()
+    {
+        metrics::enqueue(chan.vendor);
+    }
+}
+
+/// Queue `request` like a sent message and wait for the whole answer
+pub async fn complete(
+    chan: &VendorChan,
+    sse: sse_emitter::Sse,
+    mem: memory_emitter::Memory,
+    request: ChatRequest,
+) -> Result<String, String> {
+    let (done_tx, done_rx) = oneshot::channel();
+    enqueue(chan, sse, mem, request, Some(done_tx));
+    done_rx
+        .await
+        .map_err(|_| format!("{} worker stopped", chan.vendor))?
+}
+
+/// Start the worker answering the requests queued for `client`
+pub fn setup_chan<V: Vendor>(client: V) -> VendorChan {
+    let client = Arc::new(client);
+    let (tx, rx) = mpsc::unbounded_channel();
+    let _task = tokio::spawn(async move {
+        listening(rx, client).await;
+    });
+    VendorChan {
+        vendor: V::NAME,
+        tx,
+    }
+}
+
+pub fn with_vendor(
+    chan: VendorChan,
+) -> impl Filter<Extract = (VendorChan,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || chan.clone())
+}
+
+async fn listening<V: Vendor>(mut rx: mpsc::UnboundedReceiver<Job>, client: Arc<V>) {
+    while let Some((sse, mem, mut request, queued, done)) = rx.recv().await {
+        metrics::dequeue(V::NAME);
+        let parent = queued.dequeue();
+        request.persona = client
+            .config()
+            .load()
+            .personas
+            .select(mem.persona(&request.uuid).as_deref());
+        let model = client.model(&request);
+        let span = tracing::info_span!(
+            parent: &parent,
+            "vendor_request",
+            conversation_id = %request.uuid,
+            vendor = V::NAME,
+            model = %model,
+        );
+        let client = client.clone();
+        tokio::spawn(
+            async move {
+                let (conversation, user) = (request.uuid.clone(), request.user.clone());
+                let result = request_to_vendor(client.as_ref(), sse, mem, request)
+                    .await
+                    .map_err(|err| err.to_string());
+                if let Ok(answer) = &result {
+                    webhook::notify(
+                        &client.config().load(),
+                        webhook::ANSWER_COMPLETED,
+                        json!({
+                            "conversation": conversation.as_str(),
+                            "user": user.as_deref(),
+                            "vendor": V::NAME,
+                            "model": model,
+                            "answer": answer,
+                        }),
+                    );
+                }
+                if let Some(done) = done {
+                    let _ = done.send(result);
+                }
+            }
+            .instrument(span),
+        );
+    }
+}
+
+async fn request_to_vendor<V: Vendor>(
+    client: &V,
+    sse: sse_emitter::Sse,
+    mem: memory_emitter::Memory,
+    request: ChatRequest,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let memory = memory_emitter::get_memory(mem.clone(), request.uuid.clone()).await;
+
+    // Record lastest user input message
+    let new_input = Arc::new(format!("user:{}[[stop]]", request.message));
+    memory_emitter::record(mem.clone(), request.uuid.clone(), new_input).await;
+
+    let model = client.model(&request);
+    let inputs = match client.inputs(&request, &model).await {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            tracing::warn!(error = %err, "attachments rejected");
+            let reply = Arc::new(format!("Unable to use the attachments: {:#}", err));
+            publish(&sse, &request, Message::Reply(reply.clone())).await;
+            memory_emitter::record(mem.clone(), request.uuid.clone(), reply).await;
+            stop(&sse, &mem, &request).await;
+            return Err(err.into());
+        }
+    };
+
+    // Keep answering tool calls until the model produces a final answer
+    let plugins = match &request.persona {
+        Some(persona) => persona.tools_for(request.plugins.as_deref()),
+        None => request.plugins.clone(),
+    };
+    let toolset = client.toolset(
+        request.uuid.clone(),
+        request.user.clone(),
+        plugins.as_deref(),
+    );
+    let mut exchange = ToolExchange::default();
+    let mut answer = String::new();
+    loop {
+        let vendor_request = client.create_request(
+            &model,
+            &request,
+            &inputs,
+            Some(memory.clone()),
+            &exchange,
+            &toolset,
+        );
+        let turn = match stream(
+            client,
+            &model,
+            &sse,
+            &mem,
+            &request,
+            vendor_request,
+            &mut answer,
+        )
+        .await?
+        {
+            Some(turn) => turn,
+            None => return Ok(answer),
+        };
+
+        if exchange.rounds() >= client.max_tool_rounds() {
+            tracing::warn!(rounds = exchange.rounds(), "tool call limit reached");
+            stop(&sse, &mem, &request).await;
+            return Ok(answer);
+        }
+        client.use_tools(turn, &mut exchange, &toolset).await;
+    }
+}
+
+/// Stream one response into `answer`, returning the turn when the model asks for tools
+#[tracing::instrument(name = "llm", skip_all, fields(ttft_ms = tracing::field::Empty))]
+async fn stream<V: Vendor>(
+    client: &V,
+    model: &str,
+    sse: &sse_emitter::Sse,
+    mem: &memory_emitter::Memory,
+    request: &ChatRequest,
+    vendor_request: reqwest::RequestBuilder,
+    answer: &mut String,
+) -> Result<Option<V::Turn>, Box<dyn std::error::Error>> {
+    let mut timer = metrics::StreamTimer::start(V::NAME, model);
+    let mut turn = V::Turn::default();
+    let mut es = EventSource::new(vendor_request).expect("Failed to create EventSource");
+    while let Some(event) = es.next().await {
+        match event {
+            Ok(Event::Open) => tracing::debug!("connection open"),
+            Ok(Event::Message(message)) => match client.process(&message.data, &mut turn) {
+                Ok(MessageAction::SendBody(body)) => {
+                    timer.token();
+                    answer.push_str(&body);
+                    publish(sse, request, Message::Reply(body.clone())).await;
+                    memory_emitter::record(mem.clone(), request.uuid.clone(), body).await;
+                }
+                Ok(MessageAction::ToolUse) => {
+                    es.close();
+                    timer.finish(metrics::STATUS_OK);
+                    return Ok(Some(turn));
+                }
+                Ok(MessageAction::Stop) => {
+                    stop(sse, mem, request).await;
+                    es.close();
+                    timer.finish(metrics::STATUS_OK);
+                    return Ok(None);
+                }
+                Ok(MessageAction::NoAction) => (),
+                Err(err) => tracing::warn!(error = %err, "failed to parse message"),
+            },
+            Err(err) => {
+                tracing::error!(error = %err, "vendor stream failed");
+                stop(sse, mem, request).await;
+                es.close();
+                timer.finish(metrics::STATUS_ERROR);
+                return Err(Box::new(err));
+            }
+        }
+    }
+    timer.finish(metrics::STATUS_OK);
+    Ok(None)
+}
+
+async fn stop(sse: &sse_emitter::Sse, mem: &memory_emitter::Memory, request: &ChatRequest) {
+    publish(sse, request, Message::Reply(STOP_SIGN.clone())).await;
+    memory_emitter::record(mem.clone(), request.uuid.clone(), STOP_SIGN.clone()).await;
+}
+
+/// Send `message` to the client of the conversation unless nobody listens
+async fn publish(sse: &sse_emitter::Sse, request: &ChatRequest, message: Message) {
+    if !request.background {
+        sse_emitter::publish(sse.clone(), request.uuid.clone(), message).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arc_swap::ArcSwap;
+    use serde_json::Value;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use crate::attachments::AttachmentStore;
+    use crate::config::Config;
+    use crate::vendor::{claude::Claude, openai::OpenAI};
+
+    const TOOL: &str = "get_stock_selection";
+
+    /// Stand-in for a vendor API streaming the next of `bodies` for every
+    /// request, the last one again once they run out, and keeping the payloads
+    async fn upstream(bodies: Vec<String>) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let payloads = Arc::new(Mutex::new(Vec::new()));
+        let received = payloads.clone();
+        let route = warp::post()
+            .and(warp::body::json())
+            .map(move |payload: Value| {
+                let mut received = received.lock().unwrap();
+                received.push(payload);
+                let body = &bodies[(received.len() - 1).min(bodies.len() - 1)];
+                warp::http::Response::builder()
+                    .header("content-type", "text/event-stream")
+                    .body(body.clone())
+                    .unwrap()
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (format!("http://{}", addr), payloads)
+    }
+
+    fn events(data: &[Value]) -> String {
+        data.iter()
+            .map(|data| format!("data: {}\n\n", data))
+            .collect()
+    }
+
+    fn config(base_url: &str, max_tool_rounds: usize) -> SharedConfig {
+        let mut config = Config::default();
+        for vendor in [&mut config.openai, &mut config.claude] {
+            vendor.api_key = String::from("test");
+            vendor.base_url = base_url.to_string();
+        }
+        config.plugins.enabled = vec![TOOL.to_string()];
+        config.plugins.max_tool_rounds = max_tool_rounds;
+        config.attachments.path =
+            std::env::temp_dir().join(format!("chat-handler-{}", uuid::Uuid::new_v4()));
+        Arc::new(ArcSwap::from_pointee(config))
+    }
+
+    fn attachments(config: &SharedConfig) -> Arc<AttachmentStore> {
+        Arc::new(AttachmentStore::open(&config.load().attachments).unwrap())
+    }
+
+    async fn ask(chan: &VendorChan) -> Result<String, String> {
+        let request = ChatRequest {
+            uuid: Arc::new(uuid::Uuid::new_v4().to_string()),
+            message: Arc::new(String::from("What should I buy?")),
+            image: None,
+            attachments: None,
+            user: None,
+            plugins: None,
+            model: None,
+            persona: None,
+            background: true,
+        };
+        let mem = memory_emitter::create_memory(10, Duration::from_secs(3600));
+        complete(chan, sse_emitter::create_sse(), mem, request).await
+    }
+
+    // Two calls at once, one without arguments and one with an unknown one,
+    // so both fail validation without reaching the stock API
+    fn openai_tool_calls() -> String {
+        events(&[
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 0, "id": "call_a", "function": {"name": TOOL, "arguments": "{}"}},
+                {"index": 1, "id": "call_b", "function": {"name": TOOL, "arguments": ""}},
+            ]}, "finish_reason": null}]}),
+            json!({"choices": [{"delta": {"tool_calls": [
+                {"index": 1, "function": {"arguments": "{\"day\": 1}"}},
+            ]}, "finish_reason": null}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ])
+    }
+
+    fn openai_answer() -> String {
+        events(&[
+            json!({"choices": [{"delta": {"content": "Nothing today"}, "finish_reason": null}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "stop"}]}),
+        ])
+    }
+
+    fn claude_tool_calls() -> String {
+        events(&[
+            json!({"type": "message_start"}),
+            json!({"type": "content_block_start", "index": 0,
+                "content_block": {"type": "tool_use", "id": "toolu_a", "name": TOOL}}),
+            json!({"type": "content_block_delta", "index": 0,
+                "delta": {"type": "input_json_delta", "partial_json": "{}"}}),
+            json!({"type": "content_block_start", "index": 1,
+                "content_block": {"type": "tool_use", "id": "toolu_b", "name": TOOL}}),
+            json!({"type": "content_block_delta", "index": 1,
+                "delta": {"type": "input_json_delta", "partial_json": "{\"day\": 1}"}}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}}),
+            json!({"type": "message_stop"}),
+        ])
+    }
+
+    fn claude_answer() -> String {
+        events(&[
+            json!({"type": "content_block_start", "index": 0,
+                "content_block": {"type": "text"}}),
+            json!({"type": "content_block_delta", "index": 0,
+                "delta": {"type": "text_delta", "text": "Nothing today"}}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "end_turn"}}),
+            json!({"type": "message_stop"}),
+        ])
+    }
+
+    #[tokio::test]
+    async fn openai_answers_every_tool_call_by_id() {
+        let (url, payloads) = upstream(vec![openai_tool_calls(), openai_answer()]).await;
+        let config = config(&url, 5);
+        let chan = setup_chan(OpenAI::new(config.clone(), attachments(&config)));
+
+        assert_eq!(ask(&chan).await.unwrap(), "Nothing today");
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 2);
+        let messages = payloads[1]["messages"].as_array().unwrap();
+        let calls = messages
+            .iter()
+            .find_map(|message| message["tool_calls"].as_array())
+            .unwrap();
+        let ids: Vec<_> = calls.iter().map(|call| call["id"].as_str()).collect();
+        assert_eq!(ids, [Some("call_a"), Some("call_b")]);
+        assert_eq!(calls[1]["function"]["arguments"], "{\"day\": 1}");
+
+        let results: Vec<_> = messages
+            .iter()
+            .filter(|message| message["role"] == "tool")
+            .collect();
+        assert_eq!(results.len(), 2);
+        for (result, id) in results.iter().zip(["call_a", "call_b"]) {
+            assert_eq!(result["tool_call_id"], id);
+            let content = result["content"].as_str().unwrap();
+            assert!(content.starts_with("Error:"), "{}", content);
+            assert!(content.contains("invalid_arguments"), "{}", content);
+        }
+    }
+
+    #[tokio::test]
+    async fn claude_answers_every_tool_use_by_id() {
+        let (url, payloads) = upstream(vec![claude_tool_calls(), claude_answer()]).await;
+        let config = config(&url, 5);
+        let chan = setup_chan(Claude::new(config.clone(), attachments(&config)));
+
+        assert_eq!(ask(&chan).await.unwrap(), "Nothing today");
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 2);
+        let messages = payloads[1]["messages"].as_array().unwrap();
+        let (uses, results) = match &messages[messages.len() - 2..] {
+            [uses, results] => (&uses["content"], &results["content"]),
+            _ => unreachable!(),
+        };
+        let ids: Vec<_> = uses
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|block| block["id"].as_str())
+            .collect();
+        assert_eq!(ids, [Some("toolu_a"), Some("toolu_b")]);
+        assert_eq!(uses[1]["input"], json!({"day": 1}));
+
+        let results = results.as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        for (result, id) in results.iter().zip(["toolu_a", "toolu_b"]) {
+            assert_eq!(result["type"], "tool_result");
+            assert_eq!(result["tool_use_id"], id);
+            assert_eq!(result["is_error"], true);
+            let content = result["content"].as_str().unwrap();
+            assert!(content.contains("invalid_arguments"), "{}", content);
+        }
+    }
+
+    #[tokio::test]
+    async fn openai_gives_up_after_max_tool_rounds() {
+        let (url, payloads) = upstream(vec![openai_tool_calls()]).await;
+        let config = config(&url, 2);
+        let chan = setup_chan(OpenAI::new(config.clone(), attachments(&config)));
+
+        assert_eq!(ask(&chan).await.unwrap(), "");
+        // The first request and one per round, the last calls go unanswered
+        assert_eq!(payloads.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn claude_gives_up_after_max_tool_rounds() {
+        let (url, payloads) = upstream(vec![claude_tool_calls()]).await;
+        let config = config(&url, 2);
+        let chan = setup_chan(Claude::new(config.clone(), attachments(&config)));
+
+        assert_eq!(ask(&chan).await.unwrap(), "");
+        assert_eq!(payloads.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,21 @@
+use warp::http::StatusCode;
+use warp::reply::with_status;
+
+use crate::emitter::*;
+use crate::telemetry::metrics;
+
+pub async fn scrape(
+    sse: sse_emitter::Sse,
+    mem: memory_emitter::Memory,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    metrics::set_sse_connections(sse.lock().await.active());
+    metrics::set_memory_conversations(mem.len());
+
+    match metrics::gather() {
+        Ok(body) => Ok(with_status(body, StatusCode::OK)),
+        Err(err) => Ok(with_status(
+            format!("Failed to encode metrics: {}", err),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )),
+    }
+}
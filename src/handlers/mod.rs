@@ -1,3 +1,75 @@
-pub mod claude_handler;
-pub mod openai_handler;
+use std::sync::Arc;
+use warp::Filter;
+
+use crate::api::chat::ChatRequest;
+use crate::config::SharedConfig;
+use crate::emitter::{memory_emitter, sse_emitter};
+use chat_handler::VendorChan;
+
+pub mod attachment_handler;
+pub mod chat_handler;
+pub mod metrics_handler;
+pub mod persona_handler;
+pub mod schedule_handler;
 pub mod sse_handler;
+pub mod watchlist_handler;
+pub mod webhook_handler;
+
+/// Receives the full answer, or the failure, of a queued vendor request
+pub type Completion = tokio::sync::oneshot::Sender<Result<String, String>>;
+
+/// Vendor queues and the emitters their answers go to, for requests the
+/// server makes on its own such as scheduled reports
+#[derive(Clone)]
+pub struct Pipeline {
+    pub config: SharedConfig,
+    pub sse: sse_emitter::Sse,
+    pub mem: memory_emitter::Memory,
+    pub openai: VendorChan,
+    pub claude: VendorChan,
+}
+
+/// A message the server sends to a vendor by itself
+pub struct Prompt {
+    pub conversation: Arc<String>,
+    pub message: Arc<String>,
+    pub user: Option<Arc<String>>,
+    pub plugins: Option<Vec<String>>,
+    pub model: Option<Arc<String>>,
+    /// Whether no client listens to the conversation
+    pub background: bool,
+}
+
+impl Pipeline {
+    /// Queue `prompt` with `vendor`, `openai` or `claude`, like a sent message
+    /// and wait for the whole answer
+    pub async fn complete(&self, vendor: &str, prompt: Prompt) -> Result<String, String> {
+        self.config
+            .load()
+            .check_vendor(vendor)
+            .map_err(|err| err.to_string())?;
+        let chan = match vendor {
+            "openai" => &self.openai,
+            "claude" => &self.claude,
+            _ => return Err(format!("unknown vendor {}", vendor)),
+        };
+        let request = ChatRequest {
+            uuid: prompt.conversation,
+            message: prompt.message,
+            image: None,
+            attachments: None,
+            user: prompt.user,
+            plugins: prompt.plugins,
+            model: prompt.model,
+            persona: None,
+            background: prompt.background,
+        };
+        chat_handler::complete(chan, self.sse.clone(), self.mem.clone(), request).await
+    }
+}
+
+pub fn with_pipeline(
+    pipeline: Pipeline,
+) -> impl Filter<Extract = (Pipeline,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || pipeline.clone())
+}
@@ -0,0 +1,13 @@
+use crate::config::{Persona, SharedConfig};
+
+/// Personas a send request can choose, without their prompts
+pub async fn list(config: SharedConfig) -> Result<impl warp::Reply, warp::Rejection> {
+    let config = config.load();
+    let personas: Vec<&Persona> = config
+        .personas
+        .loaded
+        .values()
+        .map(|persona| persona.as_ref())
+        .collect();
+    Ok(warp::reply::json(&personas))
+}
@@ -0,0 +1,300 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::http::StatusCode;
+use warp::reply::{with_status, Reply, Response};
+use warp::Filter;
+
+use crate::api::schedule::{Run, RunStatus, Schedule, ScheduleQuery, ScheduleRequest};
+use crate::config::{Config, SharedConfig};
+use crate::cron::Cron;
+use crate::handlers::{Pipeline, Prompt};
+use crate::store::JsonStore;
+use crate::telemetry::metrics;
+use crate::webhook;
+
+pub type Schedules = Arc<JsonStore<Schedule>>;
+
+/// How often the scheduler looks for due schedules
+const TICK: Duration = Duration::from_secs(15);
+/// Characters of an answer kept in the run history
+const MAX_OUTPUT_CHARS: usize = 4000;
+
+pub async fn create(
+    request: ScheduleRequest,
+    user: Arc<String>,
+    schedules: Schedules,
+    config: SharedConfig,
+) -> Result<Response, warp::Rejection> {
+    let config = config.load();
+    let timezone = match config.schedules.timezone() {
+        Ok(timezone) => timezone,
+        Err(err) => return Ok(error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())),
+    };
+    let schedule = match validate(request, &user, &config, timezone) {
+        Ok(schedule) => schedule,
+        Err(message) => return Ok(error(StatusCode::BAD_REQUEST, &message)),
+    };
+    if let Err(err) = schedules
+        .insert(schedule.id.clone(), schedule.clone())
+        .await
+    {
+        tracing::error!(error = %err, "failed to save schedule");
+        return Ok(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to save schedule",
+        ));
+    }
+    tracing::info!(schedule = %schedule.id, cron = %schedule.cron, "schedule created");
+    Ok(with_status(warp::reply::json(&schedule), StatusCode::CREATED).into_response())
+}
+
+/// Schedules of the caller, of one conversation if the query names it
+pub async fn list(
+    query: ScheduleQuery,
+    user: Arc<String>,
+    schedules: Schedules,
+) -> Result<Response, warp::Rejection> {
+    let found: Vec<Schedule> = schedules
+        .list()
+        .into_iter()
+        .filter(|schedule| {
+            schedule.user.as_deref() == Some(user.as_str())
+                && (query.conversation.is_none() || schedule.conversation == query.conversation)
+        })
+        .collect();
+    Ok(warp::reply::json(&found).into_response())
+}
+
+pub async fn get(
+    id: String,
+    user: Arc<String>,
+    schedules: Schedules,
+) -> Result<Response, warp::Rejection> {
+    match owned(&schedules, &id, &user) {
+        Some(schedule) => Ok(warp::reply::json(&schedule).into_response()),
+        None => Ok(error(StatusCode::NOT_FOUND, "schedule not found")),
+    }
+}
+
+/// Start a run right away, its result lands in the run history
+pub async fn run(
+    id: String,
+    user: Arc<String>,
+    schedules: Schedules,
+    pipeline: Pipeline,
+) -> Result<Response, warp::Rejection> {
+    match owned(&schedules, &id, &user) {
+        Some(schedule) => {
+            tokio::spawn(execute(pipeline, schedules, schedule));
+            Ok(with_status(
+                warp::reply::json(&json!({ "status": "started" })),
+                StatusCode::ACCEPTED,
+            )
+            .into_response())
+        }
+        None => Ok(error(StatusCode::NOT_FOUND, "schedule not found")),
+    }
+}
+
+pub async fn delete(
+    id: String,
+    user: Arc<String>,
+    schedules: Schedules,
+) -> Result<Response, warp::Rejection> {
+    if owned(&schedules, &id, &user).is_none() {
+        return Ok(error(StatusCode::NOT_FOUND, "schedule not found"));
+    }
+    match schedules.remove(&id).await {
+        Ok(Some(_)) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(None) => Ok(error(StatusCode::NOT_FOUND, "schedule not found")),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to save schedules");
+            Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to delete schedule",
+            ))
+        }
+    }
+}
+
+/// The schedule `id` if `user` owns it, schedules of other users are not found
+/// like missing ones
+fn owned(schedules: &Schedules, id: &str, user: &str) -> Option<Schedule> {
+    schedules
+        .get(id)
+        .filter(|schedule| schedule.user.as_deref() == Some(user))
+}
+
+fn validate(
+    request: ScheduleRequest,
+    user: &str,
+    config: &Config,
+    timezone: Tz,
+) -> Result<Schedule, String> {
+    let cron: Cron = request.cron.parse().map_err(|err| format!("{:#}", err))?;
+    if !matches!(request.vendor.as_str(), "openai" | "claude") {
+        return Err(String::from("vendor must be openai or claude"));
+    }
+    config
+        .check_vendor(&request.vendor)
+        .map_err(|err| err.to_string())?;
+    if request.prompt.trim().is_empty() {
+        return Err(String::from("prompt must not be empty"));
+    }
+    if request.model.as_deref().is_some_and(str::is_empty) {
+        return Err(String::from("model must not be empty when given"));
+    }
+    let next_run = next_run(&cron, timezone, Utc::now())
+        .ok_or_else(|| format!("cron expression {:?} never runs", request.cron))?;
+
+    Ok(Schedule {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: request.name.unwrap_or_else(|| request.cron.clone()),
+        cron: request.cron,
+        vendor: request.vendor,
+        model: request.model,
+        prompt: request.prompt,
+        conversation: request.conversation,
+        user: Some(user.to_string()),
+        plugins: request.plugins,
+        created_at: Utc::now(),
+        next_run: Some(next_run),
+        runs: Vec::new(),
+    })
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    with_status(warp::reply::json(&json!({ "error": message })), status).into_response()
+}
+
+fn next_run(cron: &Cron, timezone: Tz, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    cron.next_after(&after.with_timezone(&timezone))
+        .map(|next| next.with_timezone(&Utc))
+}
+
+/// Open the saved schedules and start running them. Occurrences missed while
+/// the server was down are skipped.
+pub async fn setup_schedules(pipeline: Pipeline) -> Result<Schedules> {
+    let config = pipeline.config.load_full();
+    let schedules: Schedules = Arc::new(JsonStore::open(&config.schedules.path)?);
+    let timezone = config.schedules.timezone()?;
+    let now = Utc::now();
+    schedules
+        .update_all(|schedule| {
+            schedule.next_run = schedule
+                .cron
+                .parse()
+                .ok()
+                .and_then(|cron| next_run(&cron, timezone, now));
+        })
+        .await?;
+    tracing::info!(
+        count = schedules.list().len(),
+        path = %config.schedules.path.display(),
+        "schedules loaded"
+    );
+    tokio::spawn(schedule(pipeline, schedules.clone()));
+    Ok(schedules)
+}
+
+pub fn with_schedules(
+    schedules: Schedules,
+) -> impl Filter<Extract = (Schedules,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || schedules.clone())
+}
+
+async fn schedule(pipeline: Pipeline, schedules: Schedules) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let timezone = match pipeline.config.load().schedules.timezone() {
+            Ok(timezone) => timezone,
+            Err(err) => {
+                tracing::error!(error = %err, "schedules paused");
+                continue;
+            }
+        };
+        let now = Utc::now();
+        for schedule in schedules.list() {
+            if schedule.next_run.is_none_or(|next| next > now) {
+                continue;
+            }
+            // Move on before running so a slow run is not started twice
+            let next = schedule
+                .cron
+                .parse()
+                .ok()
+                .and_then(|cron| next_run(&cron, timezone, now));
+            let saved = schedules
+                .update(&schedule.id, |schedule| schedule.next_run = next)
+                .await;
+            if let Err(err) = saved {
+                tracing::error!(schedule = %schedule.id, error = %err, "failed to save schedule");
+            }
+            tokio::spawn(execute(pipeline.clone(), schedules.clone(), schedule));
+        }
+    }
+}
+
+/// Send the schedule's prompt through the vendor pipeline and record the run
+async fn execute(pipeline: Pipeline, schedules: Schedules, schedule: Schedule) {
+    let started_at = Utc::now();
+    let prompt = Prompt {
+        conversation: Arc::new(
+            schedule
+                .conversation
+                .clone()
+                .unwrap_or_else(|| format!("schedule-{}", schedule.id)),
+        ),
+        message: Arc::new(schedule.prompt.clone()),
+        user: schedule.user.clone().map(Arc::new),
+        plugins: schedule.plugins.clone(),
+        model: schedule.model.clone().map(Arc::new),
+        background: schedule.conversation.is_none(),
+    };
+    let result = pipeline.complete(&schedule.vendor, prompt).await;
+    metrics::schedule_run(result.is_ok());
+
+    let (status, output) = match result {
+        Ok(answer) => (RunStatus::Ok, answer),
+        Err(err) => {
+            tracing::warn!(schedule = %schedule.id, error = %err, "scheduled run failed");
+            (RunStatus::Failed, err)
+        }
+    };
+    let run = Run {
+        started_at,
+        finished_at: Utc::now(),
+        status,
+        output: output.chars().take(MAX_OUTPUT_CHARS).collect(),
+    };
+    tracing::info!(schedule = %schedule.id, status = ?run.status, "scheduled run finished");
+
+    let config = pipeline.config.load();
+    webhook::notify(
+        &config,
+        webhook::SCHEDULE_FINISHED,
+        json!({
+            "schedule": schedule.id,
+            "name": schedule.name,
+            "conversation": schedule.conversation,
+            "run": run,
+        }),
+    );
+
+    let history = config.schedules.history;
+    let saved = schedules
+        .update(&schedule.id, move |schedule| {
+            schedule.runs.push(run);
+            let excess = schedule.runs.len().saturating_sub(history);
+            schedule.runs.drain(..excess);
+        })
+        .await;
+    if let Err(err) = saved {
+        tracing::error!(schedule = %schedule.id, error = %err, "failed to save run history");
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Asia::Taipei;
+use futures::future::join_all;
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use warp::http::StatusCode;
+use warp::reply::{with_status, Reply, Response};
+use warp::Filter;
+
+use crate::api::watchlist::{Watchlist, WatchlistQuery, WatchlistRequest};
+use crate::config::{Config, PromptsConfig, SharedConfig};
+use crate::handlers::{Pipeline, Prompt};
+use crate::store::JsonStore;
+use crate::vendor;
+use crate::webhook;
+
+pub type Watchlists = Arc<JsonStore<Watchlist>>;
+
+/// How often the scheduler checks whether the report is due
+const TICK: Duration = Duration::from_secs(30);
+const MAX_STOCK_ID_LEN: usize = 10;
+
+pub static PROMPT: &str = r#"[Play as professional investor role][DO NOT respond with code advice] Write today's report of the watchlist below from the data provided:
+1. One line per stock on its close, price change and volume against the previous days.
+2. Highlight stocks foreign investors or investment trusts kept buying or selling.
+3. Note unusual broker concentration.
+Keep it short, skip stocks without data.
+"#;
+
+pub async fn create(
+    request: WatchlistRequest,
+    user: Arc<String>,
+    watchlists: Watchlists,
+    config: SharedConfig,
+) -> Result<Response, warp::Rejection> {
+    let watchlist = match validate(request, &user, config.load().watchlists.max_stocks) {
+        Ok(watchlist) => watchlist,
+        Err(message) => return Ok(error(StatusCode::BAD_REQUEST, &message)),
+    };
+    if let Err(err) = watchlists
+        .insert(watchlist.id.clone(), watchlist.clone())
+        .await
+    {
+        tracing::error!(error = %err, "failed to save watchlist");
+        return Ok(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to save watchlist",
+        ));
+    }
+    tracing::info!(watchlist = %watchlist.id, stocks = watchlist.stocks.len(), "watchlist created");
+    Ok(with_status(warp::reply::json(&watchlist), StatusCode::CREATED).into_response())
+}
+
+/// Watchlists of the caller, of one conversation if the query names it
+pub async fn list(
+    query: WatchlistQuery,
+    user: Arc<String>,
+    watchlists: Watchlists,
+) -> Result<Response, warp::Rejection> {
+    let found: Vec<Watchlist> = watchlists
+        .list()
+        .into_iter()
+        .filter(|watchlist| {
+            owned(watchlist, &user)
+                && (query.conversation.is_none() || watchlist.conversation == query.conversation)
+        })
+        .collect();
+    Ok(warp::reply::json(&found).into_response())
+}
+
+pub async fn delete(
+    id: String,
+    user: Arc<String>,
+    watchlists: Watchlists,
+) -> Result<Response, warp::Rejection> {
+    // Watchlists of other users are not found, like missing ones
+    if !watchlists
+        .get(&id)
+        .is_some_and(|watchlist| owned(&watchlist, &user))
+    {
+        return Ok(error(StatusCode::NOT_FOUND, "watchlist not found"));
+    }
+    match watchlists.remove(&id).await {
+        Ok(Some(_)) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Ok(None) => Ok(error(StatusCode::NOT_FOUND, "watchlist not found")),
+        Err(err) => {
+            tracing::error!(error = %err, "failed to save watchlists");
+            Ok(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to delete watchlist",
+            ))
+        }
+    }
+}
+
+fn owned(watchlist: &Watchlist, user: &str) -> bool {
+    watchlist.user.as_deref() == Some(user)
+}
+
+fn validate(request: WatchlistRequest, user: &str, max_stocks: usize) -> Result<Watchlist, String> {
+    let mut seen = HashSet::new();
+    let stocks: Vec<String> = request
+        .stocks
+        .into_iter()
+        .map(|stock| stock.trim().to_uppercase())
+        .filter(|stock| seen.insert(stock.clone()))
+        .collect();
+    if stocks.is_empty() || stocks.len() > max_stocks {
+        return Err(format!("a watchlist holds 1 to {} stocks", max_stocks));
+    }
+    if let Some(stock) = stocks.iter().find(|stock| {
+        stock.is_empty()
+            || stock.len() > MAX_STOCK_ID_LEN
+            || !stock.chars().all(|c| c.is_ascii_alphanumeric())
+    }) {
+        return Err(format!("invalid stock code {:?}", stock));
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    Ok(Watchlist {
+        name: request.name.unwrap_or_else(|| stocks.join(", ")),
+        id,
+        conversation: request.conversation,
+        user: Some(user.to_string()),
+        stocks,
+        created_at: Utc::now(),
+    })
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    with_status(warp::reply::json(&json!({ "error": message })), status).into_response()
+}
+
+/// Open the saved watchlists and start reporting on them at
+/// `watchlists.report_time` every trading day
+pub fn setup_watchlists(pipeline: Pipeline) -> Result<Watchlists> {
+    let path = pipeline.config.load().watchlists.path.clone();
+    let watchlists = Arc::new(JsonStore::open(&path)?);
+    tracing::info!(count = watchlists.list().len(), path = %path.display(), "watchlists loaded");
+    tokio::spawn(schedule(pipeline, watchlists.clone()));
+    Ok(watchlists)
+}
+
+pub fn with_watchlists(
+    watchlists: Watchlists,
+) -> impl Filter<Extract = (Watchlists,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || watchlists.clone())
+}
+
+/// Run the reports once a day, when the Taipei clock passes the report time.
+/// A restart after the report time waits for the next day.
+async fn schedule(pipeline: Pipeline, watchlists: Watchlists) {
+    let mut last_run = due(&pipeline.config.load()).then(today);
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let config = pipeline.config.load_full();
+        let date = today();
+        if last_run == Some(date) || !due(&config) {
+            continue;
+        }
+        last_run = Some(date);
+
+        match vendor::is_trading_day(&config, date) {
+            Ok(true) => run_reports(&pipeline, &watchlists, date).await,
+            Ok(false) => tracing::info!(%date, "market closed, no watchlist reports"),
+            Err(err) => tracing::error!(error = %err, "unable to read the trading calendar"),
+        }
+    }
+}
+
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Taipei).date_naive()
+}
+
+/// Whether today's report time has passed
+fn due(config: &Config) -> bool {
+    match config.watchlists.report_time() {
+        Ok(time) => Utc::now().with_timezone(&Taipei).time() >= time,
+        Err(_) => false,
+    }
+}
+
+async fn run_reports(pipeline: &Pipeline, watchlists: &Watchlists, date: NaiveDate) {
+    let watchlists = watchlists.list();
+    tracing::info!(%date, count = watchlists.len(), "running watchlist reports");
+    join_all(watchlists.iter().map(|watchlist| async {
+        match report(pipeline, watchlist, date).await {
+            Ok(report) => webhook::notify(
+                &pipeline.config.load(),
+                webhook::WATCHLIST_REPORTED,
+                json!({
+                    "watchlist": watchlist.id,
+                    "name": watchlist.name,
+                    "conversation": watchlist.conversation,
+                    "user": watchlist.user,
+                    "date": date.format("%Y%m%d").to_string(),
+                    "report": report,
+                }),
+            ),
+            Err(err) => {
+                tracing::warn!(watchlist = %watchlist.id, error = %err, "watchlist report failed")
+            }
+        }
+    }))
+    .await;
+}
+
+/// Gather the day's data of every stock, have the configured vendor summarize
+/// it and return the summary. The answer streams to the watchlist's
+/// conversation and stays in its history like any other answer.
+async fn report(
+    pipeline: &Pipeline,
+    watchlist: &Watchlist,
+    date: NaiveDate,
+) -> Result<String, String> {
+    let config = pipeline.config.load_full();
+    let conversation = Arc::new(
+        watchlist
+            .conversation
+            .clone()
+            .unwrap_or_else(|| format!("watchlist-{}", watchlist.id)),
+    );
+    let user = watchlist.user.clone().map(Arc::new);
+
+    let mut message = format!(
+        "{}\nWatchlist {} on {}\n",
+        PromptsConfig::resolve(&config.prompts.watchlist, PROMPT),
+        watchlist.name,
+        date.format("%Y%m%d")
+    );
+    for stock in &watchlist.stocks {
+        let range = json!({ "stock_id": stock, "start_date": "7 days ago", "end_date": "today" });
+        let calls = [
+            ("Quotes", "get_stock_quotes", range.clone()),
+            ("Institutional trades", "get_institutional_trades", range),
+            (
+                "Concentration",
+                "get_stock_concentration",
+                json!({ "stock_id": stock, "date": "today" }),
+            ),
+        ];
+        message.push_str(&format!("\n### {}\n", stock));
+        for (label, name, args) in calls {
+            let data = vendor::call_plugin(
+                name,
+                args,
+                config.clone(),
+                conversation.clone(),
+                user.clone(),
+            )
+            .await
+            .unwrap_or_else(|err| format!("Error: {}", err));
+            message.push_str(&format!("{}: {}\n", label, data));
+        }
+    }
+
+    // The data is in the message, the report needs no tools
+    let prompt = Prompt {
+        conversation,
+        message: Arc::new(message),
+        user,
+        plugins: Some(Vec::new()),
+        model: None,
+        background: watchlist.conversation.is_none(),
+    };
+    pipeline.complete(&config.watchlists.vendor, prompt).await
+}
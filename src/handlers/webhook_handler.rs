@@ -0,0 +1,16 @@
+use crate::api::webhook::{Delivery, DeliveryQuery};
+use crate::webhook;
+
+/// Latest webhook deliveries, newest first
+pub async fn deliveries(query: DeliveryQuery) -> Result<impl warp::Reply, warp::Rejection> {
+    let found: Vec<Delivery> = webhook::deliveries()
+        .into_iter()
+        .filter(|delivery| {
+            query
+                .event
+                .as_ref()
+                .is_none_or(|event| &delivery.event == event)
+        })
+        .collect();
+    Ok(warp::reply::json(&found))
+}
@@ -0,0 +1,66 @@
+use dashmap::DashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::Filter;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+pub type Limiter = Arc<RateLimiter>;
+
+/// Fixed one-minute window request counter per conversation
+pub struct RateLimiter {
+    inner: DashMap<String, (Instant, u32)>,
+    /// Last time conversations with an expired window were dropped
+    swept: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            inner: DashMap::new(),
+            swept: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Count a request and return whether it fits in `per_minute`, 0 means unlimited
+    pub fn allow(&self, uuid: &str, per_minute: u32) -> bool {
+        if per_minute == 0 {
+            return true;
+        }
+
+        let now = Instant::now();
+        self.sweep(now);
+        let mut entry = self.inner.entry(uuid.to_string()).or_insert((now, 0));
+        let (started, count) = entry.value_mut();
+        if now.duration_since(*started) >= WINDOW {
+            *started = now;
+            *count = 0;
+        }
+        if *count >= per_minute {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Drop the counters whose window has expired, at most once per window
+    fn sweep(&self, now: Instant) {
+        let mut swept = self.swept.lock().unwrap_or_else(|err| err.into_inner());
+        if now.duration_since(*swept) < WINDOW {
+            return;
+        }
+        *swept = now;
+        self.inner
+            .retain(|_, (started, _)| now.duration_since(*started) < WINDOW);
+    }
+}
+
+pub fn create_limiter() -> Limiter {
+    Arc::new(RateLimiter::new())
+}
+
+pub fn with_limiter(
+    limiter: Limiter,
+) -> impl Filter<Extract = (Limiter,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || limiter.clone())
+}
@@ -1,44 +1,123 @@
+use arc_swap::ArcSwap;
 use emitter::memory_emitter::create_memory;
 use emitter::memory_emitter::with_memory;
 use emitter::sse_emitter::create_sse;
 use emitter::sse_emitter::with_sse;
+use std::sync::Arc;
 use warp::Filter;
 
-use crate::handlers::claude_handler::setup_claude_chan;
-use crate::handlers::openai_handler::setup_openai_chan;
+use crate::config::{with_config, Config};
+use crate::handlers::attachment_handler::{setup_attachments, with_attachments};
+use crate::handlers::chat_handler::{setup_chan, with_vendor};
+use crate::handlers::schedule_handler::{setup_schedules, with_schedules};
+use crate::handlers::watchlist_handler::{setup_watchlists, with_watchlists};
+use crate::handlers::{with_pipeline, Pipeline};
+use crate::limiter::{create_limiter, with_limiter};
+use crate::vendor::{claude::Claude, openai::OpenAI};
 
 mod api;
+mod attachments;
+mod config;
+mod cron;
 mod emitter;
 mod handlers;
+mod limiter;
 mod routes;
+mod store;
+mod telemetry;
 mod vendor;
+mod webhook;
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
-    tracing_subscriber::fmt::init();
+    telemetry::logging::init();
 
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            tracing::error!("invalid configuration: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let port = config.server.port;
+    let static_dir = config.server.static_dir.clone();
     let sse = create_sse();
-    let mem = create_memory();
+    let mem = create_memory(config.memory.capacity, config.memory.idle());
+    let limiter = create_limiter();
+    vendor::connect_mcp(&config);
     let log = warp::log("any");
-    setup_openai_chan().await;
-    setup_claude_chan().await;
+    let config = Arc::new(ArcSwap::from_pointee(config));
+    config::watcher::watch(config.clone());
+    let attachments = match setup_attachments(config.clone()) {
+        Ok(attachments) => attachments,
+        Err(err) => {
+            tracing::error!("unable to open attachments: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    let openai = setup_chan(OpenAI::new(config.clone(), attachments.clone()));
+    let claude = setup_chan(Claude::new(config.clone(), attachments.clone()));
+    let pipeline = Pipeline {
+        config: config.clone(),
+        sse: sse.clone(),
+        mem: mem.clone(),
+        openai: openai.clone(),
+        claude: claude.clone(),
+    };
+    let watchlists = match setup_watchlists(pipeline.clone()) {
+        Ok(watchlists) => watchlists,
+        Err(err) => {
+            tracing::error!("unable to load watchlists: {:#}", err);
+            std::process::exit(1);
+        }
+    };
+    let schedules = match setup_schedules(pipeline.clone()).await {
+        Ok(schedules) => schedules,
+        Err(err) => {
+            tracing::error!("unable to load schedules: {:#}", err);
+            std::process::exit(1);
+        }
+    };
 
     // Set up CORS
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_methods(vec!["GET", "POST"])
+        .allow_methods(vec!["GET", "POST", "DELETE"])
         .allow_headers(vec!["Content-Type", "Authorization"]);
 
     // Define the directory to serve static files from.
-    let static_files_dir = "static/";
-    let static_files = warp::fs::dir(static_files_dir);
+    let static_files = warp::fs::dir(static_dir);
 
     let api = static_files
-        .or(send!(sse.clone(), mem.clone()))
-        .or(send_claude!(sse.clone(), mem.clone()))
-        .or(sse!(sse));
-    let api = api.with(cors).with(log);
+        .or(send!(
+            config.clone(),
+            sse.clone(),
+            mem.clone(),
+            openai,
+            limiter.clone()
+        ))
+        .or(attachments!(config.clone(), attachments))
+        .or(watchlists!(config.clone(), watchlists))
+        .or(schedules!(config.clone(), schedules, pipeline))
+        .or(personas!(config.clone()))
+        .or(send_claude!(
+            config,
+            sse.clone(),
+            mem.clone(),
+            claude,
+            limiter
+        ))
+        .or(webhooks!())
+        .or(metrics!(sse.clone(), mem.clone()))
+        .or(sse!(sse))
+        .recover(routes::body::recover);
+    let api = api.with(cors).with(log).with(warp::trace::request());
 
-    warp::serve(api).run(([0, 0, 0, 0], 3000)).await;
+    let (_, server) = warp::serve(api).bind_with_graceful_shutdown(([0, 0, 0, 0], port), async {
+        tokio::signal::ctrl_c().await.ok();
+    });
+    server.await;
+    telemetry::trace::shutdown();
 }
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use warp::filters::multipart::FormData;
+use warp::filters::BoxedFilter;
+use warp::{path, Filter};
+
+use super::auth::{self, Identity};
+use crate::config::SharedConfig;
+
+fn path_prefix() -> BoxedFilter<()> {
+    path!("api" / "v1" / "attachments" / ..).boxed()
+}
+
+/// Multipart upload with a `file` part. The size is checked against
+/// `attachments.max_bytes` while reading, which follows config reloads unlike
+/// a limit fixed here.
+pub fn upload(config: SharedConfig) -> BoxedFilter<(FormData, Identity)> {
+    warp::post()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(warp::multipart::form().max_length(None))
+        .and(auth::identity(config))
+        .boxed()
+}
+
+pub fn list(config: SharedConfig) -> BoxedFilter<(Arc<String>,)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn get(config: SharedConfig) -> BoxedFilter<(String, Identity)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth::identity(config))
+        .boxed()
+}
+
+pub fn content(config: SharedConfig) -> BoxedFilter<(String, Identity)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path("content"))
+        .and(warp::path::end())
+        .and(auth::identity(config))
+        .boxed()
+}
+
+pub fn delete(config: SharedConfig) -> BoxedFilter<(String, Identity)> {
+    warp::delete()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth::identity(config))
+        .boxed()
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::reject::Reject;
+use warp::Filter;
+
+use crate::config::{with_config, SharedConfig};
+
+/// User named by the request's bearer token, `None` for anonymous requests
+pub type Identity = Option<Arc<String>>;
+
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl Reject for Unauthorized {}
+
+/// Identify the caller by `Authorization: Bearer <token>` against
+/// `[auth.tokens]`, rejecting unknown tokens
+pub fn identity(config: SharedConfig) -> BoxedFilter<(Identity,)> {
+    warp::header::optional::<String>("authorization")
+        .and(with_config(config))
+        .and_then(|header: Option<String>, config: SharedConfig| async move {
+            let header = match header {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+            let token = header.strip_prefix("Bearer ").unwrap_or_default().trim();
+            match config.load().auth.user(token) {
+                Some(user) => Ok(Some(Arc::new(user.to_string()))),
+                None => Err(warp::reject::custom(Unauthorized)),
+            }
+        })
+        .boxed()
+}
+
+/// Identify the caller like `identity`, rejecting anonymous requests too
+pub fn required(config: SharedConfig) -> BoxedFilter<(Arc<String>,)> {
+    identity(config)
+        .and_then(|identity: Identity| async move {
+            identity.ok_or_else(|| warp::reject::custom(Unauthorized))
+        })
+        .boxed()
+}
@@ -0,0 +1,43 @@
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+use super::auth::Unauthorized;
+use crate::config::{with_config, SharedConfig};
+
+#[derive(Debug)]
+struct PayloadTooLarge;
+
+impl Reject for PayloadTooLarge {}
+
+/// Reject bodies larger than the currently configured `server.body_limit`
+pub fn limit(config: SharedConfig) -> BoxedFilter<()> {
+    warp::header::<u64>("content-length")
+        .and(with_config(config))
+        .and_then(|length: u64, config: SharedConfig| async move {
+            if length > config.load().server.body_limit {
+                Err(warp::reject::custom(PayloadTooLarge))
+            } else {
+                Ok(())
+            }
+        })
+        .untuple_one()
+        .boxed()
+}
+
+pub async fn recover(err: Rejection) -> Result<Response, Rejection> {
+    if err.find::<PayloadTooLarge>().is_some() {
+        Ok(
+            warp::reply::with_status("payload too large", StatusCode::PAYLOAD_TOO_LARGE)
+                .into_response(),
+        )
+    } else if err.find::<Unauthorized>().is_some() {
+        let reply =
+            warp::reply::with_status("a valid bearer token is required", StatusCode::UNAUTHORIZED);
+        Ok(warp::reply::with_header(reply, "WWW-Authenticate", "Bearer").into_response())
+    } else {
+        Err(err)
+    }
+}
@@ -1,18 +1,22 @@
 use warp::filters::BoxedFilter;
 use warp::{path, Filter};
 
-use crate::api::claude::ClaudeRequestIntermediate;
+use super::auth::{self, Identity};
+use super::body;
+use crate::api::chat::ChatRequestIntermediate;
+use crate::config::SharedConfig;
 
 fn path_prefix() -> BoxedFilter<()> {
     path!("api" / "v1" / "send" / "claude" / ..).boxed()
 }
 
-pub fn send() -> BoxedFilter<(ClaudeRequestIntermediate,)> {
-    let body = warp::body::content_length_limit(8192).and(warp::body::json());
+pub fn send(config: SharedConfig) -> BoxedFilter<(ChatRequestIntermediate, Identity)> {
+    let body = body::limit(config.clone()).and(warp::body::json());
 
     warp::post()
         .and(path_prefix())
         .and(warp::path::end())
         .and(body)
+        .and(auth::identity(config))
         .boxed()
 }
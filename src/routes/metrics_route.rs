@@ -0,0 +1,10 @@
+use warp::filters::BoxedFilter;
+use warp::path;
+use warp::Filter;
+
+pub fn metrics() -> BoxedFilter<()> {
+    warp::get()
+        .and(path!("metrics"))
+        .and(warp::path::end())
+        .boxed()
+}
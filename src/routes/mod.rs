@@ -1,3 +1,11 @@
+pub mod attachment_route;
+pub mod auth;
+pub mod body;
 pub mod claude_route;
+pub mod metrics_route;
 pub mod openai_route;
+pub mod persona_route;
+pub mod schedule_route;
 pub mod sse_route;
+pub mod watchlist_route;
+pub mod webhook_route;
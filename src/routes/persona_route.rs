@@ -0,0 +1,9 @@
+use warp::filters::BoxedFilter;
+use warp::{path, Filter};
+
+pub fn list() -> BoxedFilter<()> {
+    warp::get()
+        .and(path!("api" / "v1" / "personas"))
+        .and(warp::path::end())
+        .boxed()
+}
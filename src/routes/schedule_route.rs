@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::{path, Filter};
+
+use super::auth;
+use super::body;
+use crate::api::schedule::{ScheduleQuery, ScheduleRequest};
+use crate::config::SharedConfig;
+
+fn path_prefix() -> BoxedFilter<()> {
+    path!("api" / "v1" / "schedules" / ..).boxed()
+}
+
+pub fn create(config: SharedConfig) -> BoxedFilter<(ScheduleRequest, Arc<String>)> {
+    let body = body::limit(config.clone()).and(warp::body::json());
+
+    warp::post()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(body)
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn list(config: SharedConfig) -> BoxedFilter<(ScheduleQuery, Arc<String>)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(warp::query::<ScheduleQuery>())
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn get(config: SharedConfig) -> BoxedFilter<(String, Arc<String>)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth::required(config))
+        .boxed()
+}
+
+/// Run a schedule now, outside its cron expression
+pub fn run(config: SharedConfig) -> BoxedFilter<(String, Arc<String>)> {
+    warp::post()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path("run"))
+        .and(warp::path::end())
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn delete(config: SharedConfig) -> BoxedFilter<(String, Arc<String>)> {
+    warp::delete()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth::required(config))
+        .boxed()
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::{path, Filter};
+
+use super::auth;
+use super::body;
+use crate::api::watchlist::{WatchlistQuery, WatchlistRequest};
+use crate::config::SharedConfig;
+
+fn path_prefix() -> BoxedFilter<()> {
+    path!("api" / "v1" / "watchlists" / ..).boxed()
+}
+
+pub fn create(config: SharedConfig) -> BoxedFilter<(WatchlistRequest, Arc<String>)> {
+    let body = body::limit(config.clone()).and(warp::body::json());
+
+    warp::post()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(body)
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn list(config: SharedConfig) -> BoxedFilter<(WatchlistQuery, Arc<String>)> {
+    warp::get()
+        .and(path_prefix())
+        .and(warp::path::end())
+        .and(warp::query::<WatchlistQuery>())
+        .and(auth::required(config))
+        .boxed()
+}
+
+pub fn delete(config: SharedConfig) -> BoxedFilter<(String, Arc<String>)> {
+    warp::delete()
+        .and(path_prefix())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(auth::required(config))
+        .boxed()
+}
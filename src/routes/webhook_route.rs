@@ -0,0 +1,12 @@
+use warp::filters::BoxedFilter;
+use warp::{path, Filter};
+
+use crate::api::webhook::DeliveryQuery;
+
+pub fn deliveries() -> BoxedFilter<(DeliveryQuery,)> {
+    warp::get()
+        .and(path!("api" / "v1" / "webhooks" / "deliveries"))
+        .and(warp::path::end())
+        .and(warp::query::<DeliveryQuery>())
+        .boxed()
+}
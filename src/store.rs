@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Records keyed by id, held in memory and written to a JSON file on every
+/// change so they survive restarts
+pub struct JsonStore<T> {
+    path: PathBuf,
+    records: Mutex<BTreeMap<String, T>>,
+    /// Held from a change until its file is written, so files are written in
+    /// the order of the changes and readers never wait on the disk
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> JsonStore<T> {
+    /// Load the records saved at `path`, starting empty when the file is missing
+    pub fn open(path: &Path) -> Result<Self> {
+        let records = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("invalid store file {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("unable to read {}", path.display()))
+            }
+        };
+        Ok(JsonStore {
+            path: path.to_path_buf(),
+            records: Mutex::new(records),
+            writer: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn list(&self) -> Vec<T> {
+        self.lock().values().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<T> {
+        self.lock().get(id).cloned()
+    }
+
+    pub async fn insert(&self, id: String, record: T) -> Result<()> {
+        let _writer = self.writer.lock().await;
+        let content = {
+            let mut records = self.lock();
+            records.insert(id, record);
+            serde_json::to_vec_pretty(&*records)?
+        };
+        self.save(content).await
+    }
+
+    /// Change the record `id` in place, returning it unless it is gone
+    pub async fn update(&self, id: &str, change: impl FnOnce(&mut T)) -> Result<Option<T>> {
+        let _writer = self.writer.lock().await;
+        let (updated, content) = {
+            let mut records = self.lock();
+            let updated = records.get_mut(id).map(|record| {
+                change(record);
+                record.clone()
+            });
+            let content = match updated {
+                Some(_) => Some(serde_json::to_vec_pretty(&*records)?),
+                None => None,
+            };
+            (updated, content)
+        };
+        if let Some(content) = content {
+            self.save(content).await?;
+        }
+        Ok(updated)
+    }
+
+    /// Change every record with a single write
+    pub async fn update_all(&self, mut change: impl FnMut(&mut T)) -> Result<()> {
+        let _writer = self.writer.lock().await;
+        let content = {
+            let mut records = self.lock();
+            records.values_mut().for_each(&mut change);
+            serde_json::to_vec_pretty(&*records)?
+        };
+        self.save(content).await
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<Option<T>> {
+        let mut removed = self.remove_where(|record_id, _| record_id == id).await?;
+        Ok(removed.pop())
+    }
+
+    /// Remove every record matching `remove` with a single write, returning
+    /// the removed records
+    pub async fn remove_where(&self, mut remove: impl FnMut(&str, &T) -> bool) -> Result<Vec<T>> {
+        let _writer = self.writer.lock().await;
+        let (removed, content) = {
+            let mut records = self.lock();
+            let ids: Vec<String> = records
+                .iter()
+                .filter(|(id, record)| remove(id, record))
+                .map(|(id, _)| id.clone())
+                .collect();
+            let removed: Vec<T> = ids.iter().filter_map(|id| records.remove(id)).collect();
+            let content = match removed.is_empty() {
+                true => None,
+                false => Some(serde_json::to_vec_pretty(&*records)?),
+            };
+            (removed, content)
+        };
+        if let Some(content) = content {
+            self.save(content).await?;
+        }
+        Ok(removed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, T>> {
+        self.records.lock().expect("store poisoned")
+    }
+
+    /// Write to a temporary file first so a crash never leaves half a file
+    async fn save(&self, content: Vec<u8>) -> Result<()> {
+        let temporary = self.path.with_extension("json.tmp");
+        let written = match tokio::fs::write(&temporary, content).await {
+            Ok(()) => tokio::fs::rename(&temporary, &self.path).await,
+            Err(err) => Err(err),
+        };
+        written.with_context(|| format!("unable to save {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn changes_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("store-{}.json", uuid::Uuid::new_v4()));
+        let store = JsonStore::open(&path).unwrap();
+        for id in ["a", "b", "c", "d"] {
+            store.insert(id.to_string(), id.len()).await.unwrap();
+        }
+        assert_eq!(store.update("b", |n| *n = 2).await.unwrap(), Some(2));
+        assert_eq!(store.update("missing", |n| *n = 2).await.unwrap(), None);
+        let removed = store.remove_where(|id, _| id < "c").await.unwrap();
+        assert_eq!(removed, [1, 2]);
+        assert_eq!(store.remove("d").await.unwrap(), Some(1));
+        assert_eq!(store.remove("d").await.unwrap(), None);
+
+        let reopened: JsonStore<usize> = JsonStore::open(&path).unwrap();
+        assert_eq!(reopened.list(), [1]);
+        assert_eq!(reopened.get("c"), Some(1));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use super::trace;
+
+const LOG_LEVEL: &str = "LOG_LEVEL";
+const LOG_FORMAT: &str = "LOG_FORMAT";
+const LOG_REDACT: &str = "LOG_REDACT";
+const DEFAULT_LEVEL: &str = "info";
+
+/// Keys holding user or model generated text
+const TEXT_KEYS: [&str; 4] = ["content", "text", "arguments", "system"];
+/// Keys holding tool arguments as objects, such as Claude `tool_use` input
+const ARGUMENT_KEYS: [&str; 1] = ["input"];
+/// Keys holding image payloads, either inline base64 or URLs
+const IMAGE_KEYS: [&str; 3] = ["data", "url", "image_url"];
+
+static REDACT: Lazy<bool> = Lazy::new(|| {
+    std::env::var(LOG_REDACT)
+        .map(|val| !val.eq_ignore_ascii_case("false"))
+        .unwrap_or(true)
+});
+
+/// Install the global subscriber.
+///
+/// `LOG_LEVEL` takes an `EnvFilter` directive (e.g. `debug` or
+/// `artificial=debug,warp=info`), `LOG_FORMAT=json` switches to JSON lines.
+/// Spans are also exported over OTLP when an endpoint is configured.
+pub fn init() {
+    let filter =
+        EnvFilter::try_from_env(LOG_LEVEL).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LEVEL));
+
+    let output = match std::env::var(LOG_FORMAT) {
+        Ok(format) if format.eq_ignore_ascii_case("json") => fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+        _ => fmt::layer().boxed(),
+    };
+
+    let (export, export_error) = match trace::layer() {
+        Ok(export) => (export, None),
+        Err(err) => (None, Some(err)),
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .with(export)
+        .init();
+    if let Some(err) = export_error {
+        tracing::warn!(error = %err, "failed to create OTLP exporter, trace export disabled");
+    }
+}
+
+/// Returns a copy of a vendor payload safe to log.
+///
+/// Message contents, system prompts, tool arguments and images are replaced
+/// by their length unless `LOG_REDACT=false` is set.
+pub fn redact(payload: &Value) -> Value {
+    if !*REDACT {
+        return payload.clone();
+    }
+    redact_value(payload)
+}
+
+fn redact_value(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let redacted: Map<String, Value> = map
+                .iter()
+                .map(|(key, val)| {
+                    let val = match val {
+                        Value::String(s) if TEXT_KEYS.contains(&key.as_str()) => {
+                            Value::String(format!("[redacted {} chars]", s.chars().count()))
+                        }
+                        Value::String(s) if IMAGE_KEYS.contains(&key.as_str()) => {
+                            Value::String(format!("[redacted image {} bytes]", s.len()))
+                        }
+                        _ if ARGUMENT_KEYS.contains(&key.as_str()) => Value::String(format!(
+                            "[redacted {} chars]",
+                            val.to_string().chars().count()
+                        )),
+                        _ => redact_value(val),
+                    };
+                    (key.clone(), val)
+                })
+                .collect();
+            Value::Object(redacted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_value).collect()),
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,260 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Instant;
+
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_ERROR: &str = "error";
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+static REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new("artificial_requests_total", "Vendor requests by outcome"),
+        &["vendor", "model", "status"],
+    ))
+});
+
+static TIME_TO_FIRST_TOKEN: Lazy<HistogramVec> = Lazy::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "artificial_time_to_first_token_seconds",
+            "Seconds between sending a vendor request and the first streamed token",
+        )
+        .buckets(vec![0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0]),
+        &["vendor", "model"],
+    ))
+});
+
+static STREAM_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "artificial_stream_duration_seconds",
+            "Seconds from sending a vendor request until the stream ends",
+        )
+        .buckets(vec![
+            1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0, 320.0, 600.0,
+        ]),
+        &["vendor", "model"],
+    ))
+});
+
+static TOKENS_PER_SECOND: Lazy<HistogramVec> = Lazy::new(|| {
+    register(HistogramVec::new(
+        HistogramOpts::new(
+            "artificial_tokens_per_second",
+            "Streamed tokens (content deltas) per second after the first token",
+        )
+        .buckets(vec![1.0, 5.0, 10.0, 20.0, 40.0, 80.0, 160.0, 320.0]),
+        &["vendor", "model"],
+    ))
+});
+
+static SSE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register(IntGauge::new(
+        "artificial_sse_connections",
+        "Active SSE connections",
+    ))
+});
+
+static MEMORY_CONVERSATIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register(IntGauge::new(
+        "artificial_memory_conversations",
+        "Conversations held in memory",
+    ))
+});
+
+static TOOL_CALLS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "artificial_tool_calls_total",
+            "Tool calls dispatched per plugin",
+        ),
+        &["plugin"],
+    ))
+});
+
+static TOOL_CALL_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "artificial_tool_call_failures_total",
+            "Tool calls that returned an error per plugin",
+        ),
+        &["plugin"],
+    ))
+});
+
+/// Most distinct `plugin` labels, tools named after them are counted as `other`
+/// since the names come from the model
+const MAX_TOOL_LABELS: usize = 64;
+const OTHER_TOOL: &str = "other";
+
+static TOOL_LABELS: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+static SCHEDULE_RUNS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "artificial_schedule_runs_total",
+            "Scheduled prompt runs per status",
+        ),
+        &["status"],
+    ))
+});
+
+static WEBHOOK_DELIVERIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(IntCounterVec::new(
+        Opts::new(
+            "artificial_webhook_deliveries_total",
+            "Webhook deliveries per event and final status",
+        ),
+        &["event", "status"],
+    ))
+});
+
+static QUEUE_DEPTH: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register(IntGaugeVec::new(
+        Opts::new(
+            "artificial_vendor_queue_depth",
+            "Requests waiting in the vendor channel",
+        ),
+        &["vendor"],
+    ))
+});
+
+fn register<T>(collector: prometheus::Result<T>) -> T
+where
+    T: prometheus::core::Collector + Clone + 'static,
+{
+    let collector = collector.expect("Invalid metric definition");
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("Failed to register metric");
+    collector
+}
+
+/// Tracks timings of a single streamed vendor response.
+pub struct StreamTimer {
+    vendor: &'static str,
+    model: String,
+    started: Instant,
+    first_token: Option<Instant>,
+    tokens: u64,
+}
+
+impl StreamTimer {
+    pub fn start(vendor: &'static str, model: &str) -> Self {
+        StreamTimer {
+            vendor,
+            model: model.to_string(),
+            started: Instant::now(),
+            first_token: None,
+            tokens: 0,
+        }
+    }
+
+    /// Record a streamed content delta, the first one is also recorded as
+    /// `ttft_ms` on the current span
+    pub fn token(&mut self) {
+        if self.first_token.is_none() {
+            let now = Instant::now();
+            let ttft = now.duration_since(self.started);
+            TIME_TO_FIRST_TOKEN
+                .with_label_values(&[self.vendor, &self.model])
+                .observe(ttft.as_secs_f64());
+            tracing::Span::current().record("ttft_ms", ttft.as_millis() as u64);
+            self.first_token = Some(now);
+        }
+        self.tokens += 1;
+    }
+
+    pub fn finish(self, status: &str) {
+        let labels = [self.vendor, self.model.as_str()];
+        REQUESTS
+            .with_label_values(&[self.vendor, &self.model, status])
+            .inc();
+        STREAM_DURATION
+            .with_label_values(&labels)
+            .observe(self.started.elapsed().as_secs_f64());
+        if let Some(first_token) = self.first_token {
+            let elapsed = first_token.elapsed().as_secs_f64();
+            if elapsed > 0.0 {
+                TOKENS_PER_SECOND
+                    .with_label_values(&labels)
+                    .observe(self.tokens as f64 / elapsed);
+            }
+        }
+    }
+}
+
+pub fn tool_call(plugin: &str, success: bool) {
+    let plugin = tool_label(plugin);
+    TOOL_CALLS.with_label_values(&[plugin]).inc();
+    if !success {
+        TOOL_CALL_FAILURES.with_label_values(&[plugin]).inc();
+    }
+}
+
+/// `plugin`, or `other` once `MAX_TOOL_LABELS` other names were seen
+fn tool_label(plugin: &str) -> &str {
+    let mut labels = TOOL_LABELS.lock().expect("tool labels poisoned");
+    if labels.contains(plugin) {
+        return plugin;
+    }
+    match labels.len() < MAX_TOOL_LABELS {
+        true => {
+            labels.insert(plugin.to_string());
+            plugin
+        }
+        false => OTHER_TOOL,
+    }
+}
+
+pub fn schedule_run(success: bool) {
+    let status = if success { STATUS_OK } else { STATUS_ERROR };
+    SCHEDULE_RUNS.with_label_values(&[status]).inc();
+}
+
+pub fn webhook_delivery(event: &str, success: bool) {
+    let status = if success { STATUS_OK } else { STATUS_ERROR };
+    WEBHOOK_DELIVERIES.with_label_values(&[event, status]).inc();
+}
+
+pub fn enqueue(vendor: &str) {
+    QUEUE_DEPTH.with_label_values(&[vendor]).inc();
+}
+
+pub fn dequeue(vendor: &str) {
+    QUEUE_DEPTH.with_label_values(&[vendor]).dec();
+}
+
+pub fn set_sse_connections(count: usize) {
+    SSE_CONNECTIONS.set(count as i64);
+}
+
+pub fn set_memory_conversations(count: usize) {
+    MEMORY_CONVERSATIONS.set(count as i64);
+}
+
+/// Render all metrics in the Prometheus text exposition format
+pub fn gather() -> Result<String, anyhow::Error> {
+    // Touch lazily registered metrics so they are exported before first use
+    Lazy::force(&REQUESTS);
+    Lazy::force(&TIME_TO_FIRST_TOKEN);
+    Lazy::force(&STREAM_DURATION);
+    Lazy::force(&TOKENS_PER_SECOND);
+    Lazy::force(&SSE_CONNECTIONS);
+    Lazy::force(&MEMORY_CONVERSATIONS);
+    Lazy::force(&TOOL_CALLS);
+    Lazy::force(&TOOL_CALL_FAILURES);
+    Lazy::force(&SCHEDULE_RUNS);
+    Lazy::force(&WEBHOOK_DELIVERIES);
+    Lazy::force(&QUEUE_DEPTH);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
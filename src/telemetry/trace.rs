@@ -0,0 +1,68 @@
+use once_cell::sync::OnceCell;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const OTLP_TRACES_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT";
+const SERVICE_NAME: &str = "artificial";
+
+static PROVIDER: OnceCell<SdkTracerProvider> = OnceCell::new();
+
+/// Build the OTLP export layer when an OTLP endpoint is configured.
+///
+/// Spans are sent over OTLP/HTTP to `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`), any collector listening there works.
+/// The error is returned for the caller to log once a subscriber is installed.
+pub fn layer<S>() -> Result<Option<impl Layer<S>>, ExporterBuildError>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if std::env::var(OTLP_ENDPOINT).is_err() && std::env::var(OTLP_TRACES_ENDPOINT).is_err() {
+        return Ok(None);
+    }
+
+    let exporter = SpanExporter::builder().with_http().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+    let _ = PROVIDER.set(provider);
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Flush pending spans before the process exits
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(err) = provider.shutdown() {
+            tracing::warn!(error = %err, "failed to flush traces");
+        }
+    }
+}
+
+/// Carries the trace context of an HTTP request through a vendor channel.
+pub struct Queued {
+    parent: Span,
+    waiting: Span,
+}
+
+impl Queued {
+    /// Start timing a request waiting in the vendor channel
+    pub fn enqueue(vendor: &'static str) -> Self {
+        let parent = Span::current();
+        let waiting = tracing::info_span!(parent: &parent, "queue", vendor);
+        Queued { parent, waiting }
+    }
+
+    /// End the queue span and return the originating request span
+    pub fn dequeue(self) -> Span {
+        drop(self.waiting);
+        self.parent
+    }
+}
@@ -0,0 +1,361 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::api::webhook::{Delivery, DeliveryStatus};
+use crate::config::{Config, WebhookEndpoint};
+use crate::telemetry::metrics;
+
+pub const ANSWER_COMPLETED: &str = "answer.completed";
+pub const TOOL_FAILED: &str = "tool.failed";
+pub const SCHEDULE_FINISHED: &str = "schedule.finished";
+pub const WATCHLIST_REPORTED: &str = "watchlist.reported";
+pub const EVENTS: [&str; 4] = [
+    ANSWER_COMPLETED,
+    TOOL_FAILED,
+    SCHEDULE_FINISHED,
+    WATCHLIST_REPORTED,
+];
+
+/// Wait before the first retry, doubled for every further one
+const BACKOFF: Duration = Duration::from_secs(1);
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+/// Latest finished deliveries, newest first
+static LOG: Lazy<Mutex<VecDeque<Delivery>>> = Lazy::new(Default::default);
+
+/// POST `data` as `event` to every endpoint subscribed to it. Deliveries run
+/// in the background and end up in the delivery log.
+pub fn notify(config: &Config, event: &'static str, data: Value) {
+    let webhooks = &config.webhooks;
+    let created_at = Utc::now();
+    for endpoint in webhooks
+        .endpoints
+        .iter()
+        .filter(|endpoint| endpoint.accepts(event))
+    {
+        let delivery = Delivery {
+            id: uuid::Uuid::new_v4().to_string(),
+            event: event.to_string(),
+            url: endpoint.url.clone(),
+            status: DeliveryStatus::Failed,
+            attempts: 0,
+            response_status: None,
+            error: None,
+            created_at,
+            finished_at: created_at,
+        };
+        let body = json!({
+            "id": delivery.id,
+            "event": event,
+            "created_at": created_at,
+            "data": data,
+        })
+        .to_string();
+        let endpoint = endpoint.clone();
+        let (retries, timeout, log_size) =
+            (webhooks.retries, webhooks.timeout(), webhooks.log_size);
+        tokio::spawn(async move {
+            let delivery = deliver(&endpoint, delivery, &body, retries, timeout).await;
+            record(delivery, log_size);
+        });
+    }
+}
+
+/// The delivery log, newest first
+pub fn deliveries() -> Vec<Delivery> {
+    log().iter().cloned().collect()
+}
+
+/// Signature of a delivery sent in `X-Artificial-Signature`: the hex
+/// HMAC-SHA256 of `<timestamp>.<body>` keyed with the endpoint secret
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POST `body` until the endpoint accepts it, retrying connection failures,
+/// timeouts, 408, 429 and 5xx answers with exponential backoff
+async fn deliver(
+    endpoint: &WebhookEndpoint,
+    mut delivery: Delivery,
+    body: &str,
+    retries: u32,
+    timeout: Duration,
+) -> Delivery {
+    loop {
+        delivery.attempts += 1;
+        let timestamp = Utc::now().timestamp();
+        let result = CLIENT
+            .post(&endpoint.url)
+            .timeout(timeout)
+            .header("Content-Type", "application/json")
+            .header("X-Artificial-Event", &delivery.event)
+            .header("X-Artificial-Delivery", &delivery.id)
+            .header("X-Artificial-Timestamp", timestamp)
+            .header(
+                "X-Artificial-Signature",
+                sign(&endpoint.secret, timestamp, body),
+            )
+            .body(body.to_string())
+            .send()
+            .await;
+
+        let retry = match result {
+            Ok(response) if response.status().is_success() => {
+                delivery.status = DeliveryStatus::Delivered;
+                delivery.response_status = Some(response.status().as_u16());
+                delivery.error = None;
+                break;
+            }
+            Ok(response) => {
+                let status = response.status();
+                delivery.response_status = Some(status.as_u16());
+                delivery.error = Some(format!("endpoint answered {}", status));
+                status.is_server_error()
+                    || status == StatusCode::TOO_MANY_REQUESTS
+                    || status == StatusCode::REQUEST_TIMEOUT
+            }
+            Err(err) => {
+                delivery.response_status = None;
+                delivery.error = Some(err.to_string());
+                true
+            }
+        };
+        if !retry || delivery.attempts > retries {
+            break;
+        }
+        tokio::time::sleep(BACKOFF * 2u32.pow(delivery.attempts - 1)).await;
+    }
+    delivery.finished_at = Utc::now();
+    delivery
+}
+
+fn record(delivery: Delivery, log_size: usize) {
+    let delivered = delivery.status == DeliveryStatus::Delivered;
+    metrics::webhook_delivery(&delivery.event, delivered);
+    if delivered {
+        tracing::debug!(delivery = %delivery.id, event = %delivery.event, url = %delivery.url, "webhook delivered");
+    } else {
+        tracing::warn!(
+            delivery = %delivery.id,
+            event = %delivery.event,
+            url = %delivery.url,
+            attempts = delivery.attempts,
+            error = delivery.error.as_deref().unwrap_or_default(),
+            "webhook delivery failed"
+        );
+    }
+
+    let mut log = log();
+    log.push_front(delivery);
+    log.truncate(log_size);
+}
+
+fn log() -> std::sync::MutexGuard<'static, VecDeque<Delivery>> {
+    LOG.lock().expect("webhook log poisoned")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WebhooksConfig;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use warp::http::HeaderMap;
+    use warp::Filter;
+
+    /// A request received by the stand-in endpoint
+    struct Received {
+        path: String,
+        headers: HeaderMap,
+        body: String,
+        at: Instant,
+    }
+
+    /// Endpoint answering with `statuses` in turn, then 200, and keeping
+    /// what it received
+    async fn stand_in(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let statuses = Arc::new(Mutex::new(VecDeque::from(statuses)));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let route = warp::post()
+            .and(warp::path::param::<String>())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::bytes())
+            .map(
+                move |path: String, headers: HeaderMap, body: bytes::Bytes| {
+                    seen.lock().unwrap().push(Received {
+                        path,
+                        headers,
+                        body: String::from_utf8_lossy(&body).into_owned(),
+                        at: Instant::now(),
+                    });
+                    let status = statuses.lock().unwrap().pop_front().unwrap_or(200);
+                    warp::http::StatusCode::from_u16(status).unwrap()
+                },
+            );
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (format!("http://{}", addr), received)
+    }
+
+    fn config(retries: u32, endpoints: Vec<(String, Vec<&str>)>) -> Config {
+        let endpoints = endpoints
+            .into_iter()
+            .map(|(url, events)| WebhookEndpoint {
+                url,
+                secret: String::from("secret"),
+                events: events.into_iter().map(str::to_string).collect(),
+            })
+            .collect();
+        Config {
+            webhooks: WebhooksConfig {
+                retries,
+                endpoints,
+                ..WebhooksConfig::default()
+            },
+            ..Config::default()
+        }
+    }
+
+    /// The logged delivery to `url`, once it has finished
+    async fn logged(url: &str) -> Delivery {
+        for _ in 0..100 {
+            if let Some(delivery) = deliveries()
+                .into_iter()
+                .find(|delivery| delivery.url == url)
+            {
+                return delivery;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("no delivery to {} logged", url);
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff_until_delivered() {
+        let (base, received) = stand_in(vec![503, 429]).await;
+        let url = format!("{}/hook", base);
+        notify(
+            &config(3, vec![(url.clone(), vec![])]),
+            ANSWER_COMPLETED,
+            json!({ "answer": "42" }),
+        );
+
+        let delivery = logged(&url).await;
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.response_status, Some(200));
+        assert_eq!(delivery.error, None);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        for request in received.iter() {
+            let header = |name: &str| request.headers[name].to_str().unwrap().to_string();
+            assert_eq!(header("x-artificial-delivery"), delivery.id);
+            assert_eq!(header("x-artificial-event"), ANSWER_COMPLETED);
+            let timestamp = header("x-artificial-timestamp").parse().unwrap();
+            assert_eq!(
+                header("x-artificial-signature"),
+                sign("secret", timestamp, &request.body)
+            );
+            let body: Value = serde_json::from_str(&request.body).unwrap();
+            assert_eq!(body["data"]["answer"], "42");
+        }
+        // 1s before the first retry, 2s before the second
+        assert!(received[1].at - received[0].at >= BACKOFF);
+        assert!(received[2].at - received[1].at >= BACKOFF * 2);
+    }
+
+    #[tokio::test]
+    async fn logs_the_last_failure_after_the_retries() {
+        let (base, received) = stand_in(vec![500, 502, 503]).await;
+        let url = format!("{}/hook", base);
+        notify(
+            &config(1, vec![(url.clone(), vec![])]),
+            TOOL_FAILED,
+            json!({}),
+        );
+
+        let delivery = logged(&url).await;
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.response_status, Some(502));
+        assert!(delivery.error.unwrap().contains("502"));
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (base, received) = stand_in(vec![400]).await;
+        let url = format!("{}/hook", base);
+        notify(
+            &config(3, vec![(url.clone(), vec![])]),
+            TOOL_FAILED,
+            json!({}),
+        );
+
+        let delivery = logged(&url).await;
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sends_only_subscribed_events() {
+        let (base, received) = stand_in(Vec::new()).await;
+        let url = |path: &str| format!("{}/{}", base, path);
+        let config = config(
+            0,
+            vec![
+                (url("tools"), vec![TOOL_FAILED]),
+                (url("all"), vec![]),
+                (url("answers"), vec![ANSWER_COMPLETED, SCHEDULE_FINISHED]),
+            ],
+        );
+        notify(&config, ANSWER_COMPLETED, json!({}));
+
+        assert_eq!(logged(&url("all")).await.status, DeliveryStatus::Delivered);
+        assert_eq!(
+            logged(&url("answers")).await.status,
+            DeliveryStatus::Delivered
+        );
+        let mut paths: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.path.clone())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["all", "answers"]);
+        assert!(!deliveries()
+            .iter()
+            .any(|delivery| delivery.url == url("tools")));
+    }
+
+    #[test]
+    fn signs_timestamp_and_body() {
+        assert_eq!(
+            sign("secret", 1_700_000_000, r#"{"event":"test"}"#),
+            "sha256=e6a22eb66e93669c75e7a035a110d9a2ccfa7cdef62d0ecb361671b92718ee9f"
+        );
+    }
+
+    #[test]
+    fn signature_depends_on_every_input() {
+        let signature = sign("secret", 1, "body");
+        assert_ne!(signature, sign("other", 1, "body"));
+        assert_ne!(signature, sign("secret", 2, "body"));
+        assert_ne!(signature, sign("secret", 1, "body "));
+    }
+}
@@ -3,7 +3,8 @@
 
 let activeDiv = null;
 let currentMsg = '';
-let currentImage = '';
+let currentAttachment = '';
+let currentAttachmentName = '';
 let refreshBottom = true;
 let currentVendor = 'openai';
 let hasIndexDB = false;
@@ -102,7 +103,7 @@ $(document).ready(function() {
     }
 
     addMessageRow('user');
-    formatMessage(message + '\n' + currentImage, true);
+    formatMessage(message + attachedLine(), true);
 
     var xhr = new XMLHttpRequest();
     xhr.open('POST', origin + '/api/v1/send/' + currentVendor, true);
@@ -114,12 +115,11 @@ $(document).ready(function() {
 
     let storedMessage = message;
 
-    // append image url if exists
-    if (currentImage !== '') {
-      data['image'] = currentImage;
+    // attach the uploaded file if exists
+    if (currentAttachment !== '') {
+      data['attachments'] = [currentAttachment];
+      storedMessage += attachedLine();
       removeImage();
-
-      storedMessage += '\n' + currentImage;
     }
     storeMessage('user', storedMessage);
 
@@ -365,65 +365,46 @@ function toggleLightMode() {
   document.body.classList.remove('dark-mode');
 }
 
-function uploadImageToImgur(file) {
-  const clientId = '507bd7729a21e71'; // Replace with your Imgur client ID
+function uploadAttachment(file) {
   const formData = new FormData();
-  formData.append('image', file);
+  formData.append('file', file);
 
-  fetch('https://api.imgur.com/3/image', {
+  fetch(window.location.origin + '/api/v1/attachments', {
     method: 'POST',
-    headers: {
-      Authorization: 'Client-ID ' + clientId,
-    },
     body: formData,
   })
-    .then((response) => response.json())
-    .then((data) => {
-      if (data.success) {
-        console.log('Image uploaded successfully:', data.data.link);
-        currentImage = data.data.link;
-        // You can now use the URL (data.data.link) as needed
+    .then((response) =>
+      response.json().then((data) => ({ ok: response.ok, data: data })),
+    )
+    .then(({ ok, data }) => {
+      if (ok) {
+        console.log('Attachment uploaded successfully:', data.id);
+        currentAttachment = data.id;
+        currentAttachmentName = file.name;
         const thumbnailContainer =
           document.getElementById('thumbnailContainer');
         thumbnailContainer.innerHTML = `
-          <img src="${parseThumbnail(data.data.link)}" class='thumbnail' alt='Thumbnail'>
+          <img src="${URL.createObjectURL(file)}" class='thumbnail' alt='Thumbnail'>
           <button class='delete-btn' onclick='removeImage()'> X </button>
       `;
       } else {
-        console.error('Image upload failed:', data);
+        console.error('Attachment upload failed:', data.error);
       }
     })
     .catch((error) => {
-      console.error('Error uploading image:', error);
+      console.error('Error uploading attachment:', error);
     });
 }
 
+function attachedLine() {
+  return currentAttachment !== '' ? '\nAttached: ' + currentAttachmentName : '';
+}
+
 function removeImage() {
   const thumbnailContainer = document.getElementById('thumbnailContainer');
   thumbnailContainer.innerHTML = '';
-  currentImage = '';
-}
-
-function parseThumbnail(filename) {
-  // Find the last dot in the filename to separate the name and extension
-  const lastDotIndex = filename.lastIndexOf('.');
-
-  // If there's no dot, return the original filename
-  if (lastDotIndex === -1) {
-    return filename;
-  }
-
-  // Split the filename into name and extension
-  const name = filename.substring(0, lastDotIndex);
-  const extension = filename.substring(lastDotIndex);
-
-  // Add 's' to the name
-  const newName = name + 'l';
-
-  // Reconstruct the filename
-  const newFilename = newName + extension;
-
-  return newFilename;
+  currentAttachment = '';
+  currentAttachmentName = '';
 }
 
 function storeMessage(owner, message) {